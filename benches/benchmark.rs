@@ -1,4 +1,6 @@
-use advent_of_code_2022::{get_data, get_day, get_days};
+use std::io::Cursor;
+
+use advent_of_code_2022::{day17, get_data, get_day, get_days};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 fn criterion_benchmark(c: &mut Criterion) {
@@ -18,5 +20,30 @@ fn criterion_benchmark(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn large_city_benchmark(c: &mut Criterion) {
+    // A generated 1000x1000 city, far larger than the puzzle input.
+    let mut seed = 17u64;
+    let city = (0..1000)
+        .map(|_| {
+            (0..1000)
+                .map(|_| {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (b'1' + (seed >> 33) as u8 % 9) as char
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    c.bench_function("day 17 large city A", |b| {
+        b.iter(|| day17::star_one(Cursor::new(&city)))
+    });
+    c.bench_function("day 17 large city B", |b| {
+        b.iter(|| day17::star_two(Cursor::new(&city)))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark, large_city_benchmark);
 criterion_main!(benches);
@@ -1,12 +1,171 @@
 use std::env;
 use std::fs;
 
-use std::io::BufReader;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
 
+use advent_of_code_2022::day05::{Almanac, Range};
+use advent_of_code_2022::day10::{Maze, Style};
+use advent_of_code_2022::day16::{Direction, Grid};
+use advent_of_code_2022::day17;
+use advent_of_code_2022::day18::DigPlan;
+use advent_of_code_2022::day19;
 use advent_of_code_2022::*;
 
+fn day05_query(command: &str, values: &[usize]) {
+    let almanac = Almanac::parse(&fs::read_to_string(get_day(5).2).unwrap());
+
+    match (command, values) {
+        ("trace", seeds) => {
+            for &seed in seeds {
+                let trace = almanac.trace(seed);
+                println!(
+                    "{}",
+                    trace
+                        .iter()
+                        .map(|(category, value)| format!("{category} {value}"))
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                );
+            }
+        }
+        ("reverse", &[start, end]) => {
+            for (category, ranges) in almanac.reverse(Range::new(start, end)) {
+                println!("{category}: {ranges:?}");
+            }
+        }
+        _ => panic!("Usage: 5 trace <seed>... | 5 reverse <location start> <location end>"),
+    }
+}
+
+fn day10_render(args: &[String]) {
+    let style = match args.first().map(|s| s.as_str()) {
+        Some("ansi") | None => Style::Ansi,
+        Some("plain") => Style::Plain,
+        Some(x) => panic!("Unknown style {x}, expected plain or ansi"),
+    };
+    let path = args
+        .get(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_day(10).2);
+
+    print!("{}", Maze::parse(get_data(&path)).render(style));
+}
+
+fn day16_render(command: &str, args: &[String]) {
+    let usage = "Usage: 16 beam <x> <y> <U|D|L|R> [path] | 16 heat [text|ppm] [path]";
+    let grid = |path: Option<&String>| {
+        let path = path.map(PathBuf::from).unwrap_or_else(|| get_day(16).2);
+        fs::read_to_string(path).unwrap().parse::<Grid>().unwrap()
+    };
+
+    match (command, args) {
+        ("beam", [x, y, dir, path @ ..]) => {
+            let dir = Direction::try_from(dir.chars().next().unwrap()).unwrap();
+            let start = ((x.parse().unwrap(), y.parse().unwrap()), dir);
+            let grid = grid(path.first());
+            println!("{}", grid.render_energised(start));
+            print!("{}", grid.render_directions(start));
+        }
+        ("heat", [format, path @ ..]) if format == "ppm" => {
+            let ppm = grid(path.first()).heat_map_ppm();
+            std::io::stdout().write_all(&ppm).unwrap();
+        }
+        ("heat", [format, path @ ..]) if format == "text" => {
+            print!("{}", grid(path.first()).render_heat_map());
+        }
+        ("heat", []) => print!("{}", grid(None).render_heat_map()),
+        _ => panic!("{usage}"),
+    }
+}
+
+fn day17_route(args: &[String]) {
+    let [min_step, max_step, path @ ..] = args else {
+        panic!(
+            "Usage: 17 route <min step> <max step> [path] (the route ends after a full min step)"
+        );
+    };
+    let path = path
+        .first()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_day(17).2);
+    let grid = fs::read_to_string(path)
+        .unwrap()
+        .parse::<day17::Grid>()
+        .unwrap();
+
+    match day17::process(&grid, min_step.parse().unwrap(), max_step.parse().unwrap()) {
+        Some(route) => {
+            print!("{}", route.render(&grid));
+            println!("Heat loss: {}", route.heat);
+        }
+        None => println!("No route"),
+    }
+}
+
+fn day18_svg(args: &[String]) {
+    let decode = match args.first().map(|s| s.as_str()) {
+        Some("1") | None => false,
+        Some("2") => true,
+        Some(x) => panic!("Unknown part {x}, expected 1 or 2"),
+    };
+    let path = args
+        .get(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_day(18).2);
+    let plan = fs::read_to_string(path)
+        .unwrap()
+        .parse::<DigPlan>()
+        .unwrap();
+
+    print!("{}", plan.svg(decode, 1000.0).unwrap());
+}
+
+fn day19_explain(args: &[String]) {
+    let path = args
+        .first()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_day(19).2);
+
+    print!(
+        "{}",
+        day19::explain(&fs::read_to_string(path).unwrap()).unwrap()
+    );
+}
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
+    if let [day, command, values @ ..] = &args[..] {
+        if day == "5" && (command == "trace" || command == "reverse") {
+            let values = values
+                .iter()
+                .map(|v| v.parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
+            day05_query(command, &values);
+            return;
+        }
+        if day == "10" && command == "render" {
+            day10_render(values);
+            return;
+        }
+        if day == "16" && (command == "beam" || command == "heat") {
+            day16_render(command, values);
+            return;
+        }
+        if day == "17" && command == "route" {
+            day17_route(values);
+            return;
+        }
+        if day == "18" && command == "svg" {
+            day18_svg(values);
+            return;
+        }
+        if day == "19" && command == "explain" {
+            day19_explain(values);
+            return;
+        }
+    }
+
     let days = if args.len() == 1 && args[0] == "all" {
         (1..=25).map(|x| x.to_string()).collect()
     } else {
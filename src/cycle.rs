@@ -0,0 +1,174 @@
+//! Cycle detection for simulations that are run for far more steps than is practical, such as
+//! the billion spin cycles of day 14. Given a starting state and a step function these find the
+//! state after a target number of steps, skipping whole periods once the states start repeating.
+
+use std::{collections::HashMap, hash::Hash};
+
+/// The shape of a sequence of states that eventually repeats: the first `tail` states are seen
+/// once and after that the states repeat every `period` steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub tail: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /// Maps a step to the earliest step that has the same state.
+    pub fn earliest_equivalent(&self, step: usize) -> usize {
+        if step < self.tail {
+            step
+        } else {
+            self.tail + (step - self.tail) % self.period
+        }
+    }
+}
+
+/// Finds the state after `target` steps using Brent's algorithm, which only keeps two states
+/// in memory. The step function may be called more than once for the same state.
+///
+/// Returns the cycle if one was found before reaching `target`.
+pub fn brent<S: Clone + Eq>(
+    initial: S,
+    mut step: impl FnMut(&S) -> S,
+    target: usize,
+) -> (S, Option<Cycle>) {
+    if target == 0 {
+        return (initial, None);
+    }
+
+    // Find the period by moving the hare and teleporting the tortoise to it at every power of
+    // two.
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    let mut hare_step = 1;
+
+    while tortoise != hare {
+        if hare_step == target {
+            return (hare, None);
+        }
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = step(&hare);
+        hare_step += 1;
+        period += 1;
+    }
+
+    // Find the start of the cycle by walking two states `period` apart until they meet.
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+    for _ in 0..period {
+        hare = step(&hare);
+    }
+    let mut tail = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail += 1;
+    }
+
+    let cycle = Cycle { tail, period };
+    let index = cycle.earliest_equivalent(target);
+    let (mut state, start) = if index >= tail {
+        (tortoise, tail)
+    } else {
+        (initial, 0)
+    };
+    for _ in start..index {
+        state = step(&state);
+    }
+
+    (state, Some(cycle))
+}
+
+/// Finds the state after `target` steps by remembering every state seen so far. This uses more
+/// memory than [`brent`] but the step function is called exactly once per step, in order, so it
+/// can be used to record anything else about each step.
+///
+/// Returns the cycle if one was found before reaching `target`.
+pub fn hashed<S: Clone + Eq + Hash>(
+    initial: S,
+    mut step: impl FnMut(&S) -> S,
+    target: usize,
+) -> (S, Option<Cycle>) {
+    let mut seen = HashMap::new();
+    let mut history = Vec::new();
+    let mut state = initial;
+
+    for i in 0..target {
+        if let Some(&tail) = seen.get(&state) {
+            let cycle = Cycle {
+                tail,
+                period: i - tail,
+            };
+            let state = history.swap_remove(cycle.earliest_equivalent(target));
+            return (state, Some(cycle));
+        }
+        seen.insert(state.clone(), i);
+        history.push(state.clone());
+        state = step(&state);
+    }
+
+    (state, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// x -> x^2 + 1 mod 255 has a tail before it starts repeating.
+    fn step(x: &u64) -> u64 {
+        (x * x + 1) % 255
+    }
+
+    fn naive(initial: u64, target: usize) -> u64 {
+        (0..target).fold(initial, |x, _| step(&x))
+    }
+
+    #[test]
+    fn test_brent_and_hashed_agree() {
+        for initial in 0..20 {
+            for target in 0..100 {
+                let expected = naive(initial, target);
+                assert_eq!(brent(initial, step, target).0, expected);
+                assert_eq!(hashed(initial, step, target).0, expected);
+            }
+
+            let (_, brent_cycle) = brent(initial, step, 1_000_000_000);
+            let (state, hashed_cycle) = hashed(initial, step, 1_000_000_000);
+            assert_eq!(brent_cycle, hashed_cycle);
+            let cycle = hashed_cycle.unwrap();
+            assert_eq!(
+                state,
+                naive(initial, cycle.earliest_equivalent(1_000_000_000))
+            );
+        }
+    }
+
+    #[test]
+    fn test_cycle_shape() {
+        // 0 -> 1 -> 2 -> 3 -> 4 -> 2
+        let step = |x: &u64| if *x == 4 { 2 } else { x + 1 };
+        let cycle = Cycle { tail: 2, period: 3 };
+        assert_eq!(brent(0, step, 100), (naive_with(step, 100), Some(cycle)));
+        assert_eq!(hashed(0, step, 100), (naive_with(step, 100), Some(cycle)));
+        assert_eq!(cycle.earliest_equivalent(1), 1);
+        assert_eq!(cycle.earliest_equivalent(5), 2);
+        assert_eq!(cycle.earliest_equivalent(9), 3);
+    }
+
+    fn naive_with(step: impl Fn(&u64) -> u64, target: usize) -> u64 {
+        (0..target).fold(0, |x, _| step(&x))
+    }
+
+    #[test]
+    fn test_no_cycle_before_target() {
+        let step = |x: &u64| x + 1;
+        assert_eq!(brent(0, step, 50), (50, None));
+        assert_eq!(hashed(0, step, 50), (50, None));
+    }
+}
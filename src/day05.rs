@@ -1,13 +1,13 @@
 use std::{cmp::Reverse, collections::HashMap, io::BufRead};
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
-struct Range {
-    start: usize,
-    end: usize,
+pub struct Range {
+    pub start: usize,
+    pub end: usize,
 }
 
 impl Range {
-    fn new(start: usize, end: usize) -> Self {
+    pub fn new(start: usize, end: usize) -> Self {
         Range { start, end }
     }
 
@@ -49,6 +49,18 @@ impl Range {
         self.start <= other.start && other.start < self.end
     }
 
+    /// Returns the parts of this range not covered by `other`.
+    fn without(&self, other: &Range) -> Vec<Range> {
+        let mut ranges = Vec::new();
+        if self.start < other.start {
+            ranges.push(Range::new(self.start, self.end.min(other.start)));
+        }
+        if self.end > other.end {
+            ranges.push(Range::new(self.start.max(other.end), self.end));
+        }
+        ranges
+    }
+
     fn minus(&self, other: &Range) -> Vec<Range> {
         let mut ranges = Vec::new();
         let self_range = Range::new(self.start, self.end);
@@ -125,6 +137,164 @@ impl RangeMap {
             None
         }
     }
+
+    /// Returns the source range that maps onto the part of `range` covered by this map's destination.
+    fn inv_range(&self, range: &Range) -> Option<Range> {
+        let start = range.start.max(self.destination);
+        let end = range.end.min(self.destination + self.length);
+        if start < end {
+            Some(Range::new(
+                self.inv_get(start).unwrap(),
+                self.inv_get(end - 1).unwrap() + 1,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// A single `X-to-Y map` section of the almanac.
+#[derive(Debug, Clone)]
+pub struct CategoryMap {
+    pub source: String,
+    pub destination: String,
+    ranges: Vec<RangeMap>,
+}
+
+impl CategoryMap {
+    fn get(&self, value: usize) -> usize {
+        match self
+            .ranges
+            .binary_search_by_key(&value, |range| range.source)
+        {
+            Ok(i) => self.ranges[i].destination,
+            Err(0) => value,
+            Err(i) => self.ranges[i - 1].get(value).unwrap_or(value),
+        }
+    }
+
+    /// Returns every range of source values that maps into `range`.
+    fn preimage(&self, range: &Range) -> Vec<Range> {
+        let mut preimage = self
+            .ranges
+            .iter()
+            .filter_map(|range_map| range_map.inv_range(range))
+            .collect::<Vec<_>>();
+
+        // Values not covered by any source range map onto themselves.
+        let mut unmapped = vec![range.clone()];
+        for range_map in &self.ranges {
+            let source = Range::new(range_map.source, range_map.source + range_map.length);
+            unmapped = unmapped
+                .into_iter()
+                .flat_map(|range| range.without(&source))
+                .collect();
+        }
+        preimage.extend(unmapped);
+
+        collapse_ranges(preimage)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Almanac {
+    pub seeds: Vec<usize>,
+    maps: Vec<CategoryMap>,
+}
+
+impl Almanac {
+    pub fn parse(input: &str) -> Self {
+        let mut sections = input.split("\n\n");
+
+        let seeds = sections
+            .next()
+            .unwrap()
+            .split_once(": ")
+            .unwrap()
+            .1
+            .split_whitespace()
+            .map(|s| s.parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut maps = sections
+            .map(|section| {
+                let mut lines = section.lines();
+                let line = lines.next().unwrap();
+                let (source, destination) =
+                    line.trim_end_matches(" map:").split_once("-to-").unwrap();
+
+                let mut ranges = lines
+                    .map(|line| {
+                        let mut parts = line.split_whitespace();
+                        let destination_value = parts.next().unwrap().parse::<usize>().unwrap();
+                        let source_value = parts.next().unwrap().parse::<usize>().unwrap();
+                        let range = parts.next().unwrap().parse::<usize>().unwrap();
+
+                        RangeMap::new(source_value, destination_value, range)
+                    })
+                    .collect::<Vec<_>>();
+                ranges.sort_by_key(|range| range.source);
+
+                CategoryMap {
+                    source: source.to_string(),
+                    destination: destination.to_string(),
+                    ranges,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Order the maps so that they form a chain starting from `seed`.
+        let mut chain = Vec::with_capacity(maps.len());
+        let mut current = "seed".to_string();
+        while let Some(i) = maps.iter().position(|map| map.source == current) {
+            let map = maps.swap_remove(i);
+            current = map.destination.clone();
+            chain.push(map);
+        }
+        assert!(
+            maps.is_empty(),
+            "Almanac maps do not form a chain: {maps:?}"
+        );
+
+        Almanac { seeds, maps: chain }
+    }
+
+    /// Returns the value of `seed` in every category, from `seed` through to `location`.
+    pub fn trace(&self, seed: usize) -> Vec<(&str, usize)> {
+        let mut trace = vec![("seed", seed)];
+        let mut value = seed;
+        for map in &self.maps {
+            value = map.get(value);
+            trace.push((map.destination.as_str(), value));
+        }
+        trace
+    }
+
+    pub fn location(&self, seed: usize) -> usize {
+        self.maps.iter().fold(seed, |value, map| map.get(value))
+    }
+
+    /// Maps a range of locations back through every category. The result starts at `location`
+    /// with the given range and ends with the seed ranges that produce it.
+    pub fn reverse(&self, locations: Range) -> Vec<(&str, Vec<Range>)> {
+        let mut stages = vec![("location", vec![locations.clone()])];
+        let mut ranges = vec![locations];
+        for map in self.maps.iter().rev() {
+            ranges = collapse_ranges(
+                ranges
+                    .iter()
+                    .flat_map(|range| map.preimage(range))
+                    .collect(),
+            );
+            stages.push((map.source.as_str(), ranges.clone()));
+        }
+        stages
+    }
+
+    /// Returns the seed ranges that end up at a location within `locations`.
+    pub fn seeds_for(&self, locations: Range) -> Vec<Range> {
+        self.reverse(locations).pop().unwrap().1
+    }
 }
 
 #[cfg(test)]
@@ -317,84 +487,25 @@ pub fn star_one(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
-    let mut sections = str.split("\n\n");
-
-    let needed_seeds = {
-        let line = sections.next().unwrap();
-
-        line.split_once(": ")
-            .unwrap()
-            .1
-            .split_whitespace()
-            .map(|s| s.parse::<usize>().unwrap())
-            .collect::<Vec<_>>()
-    };
-
-    let mut conversion_map = HashMap::new();
-
-    let mut type_mapping = HashMap::new();
-
-    for section in sections {
-        let mut ranges = Vec::new();
-
-        let mut lines = section.lines();
-        let line = lines.next().unwrap();
-        let line = line.replace("-to-", "-");
-        let line = line.replace(" map:", "");
-
-        let (source, destination) = line.split_once('-').unwrap();
-        let source = source.to_string();
-        let destination = destination.to_string();
-
-        type_mapping.insert(source.clone(), destination.clone());
-
-        for line in lines {
-            let mut parts = line.split_whitespace();
-            let destination_value = parts.next().unwrap().parse::<usize>().unwrap();
-            let source_value = parts.next().unwrap().parse::<usize>().unwrap();
-            let range = parts.next().unwrap().parse::<usize>().unwrap();
-
-            ranges.push(RangeMap::new(source_value, destination_value, range));
-        }
-        ranges.sort_by_key(|range| range.source);
-        conversion_map.insert((source, destination), ranges);
-    }
+    let almanac = Almanac::parse(&str);
 
-    needed_seeds
+    almanac
+        .seeds
         .iter()
-        .map(|&seed| {
-            let mut value_type = "seed".to_string();
-            let mut value = seed;
-
-            while value_type != "location" {
-                let new_value_type = type_mapping.get(&value_type).unwrap().clone();
-
-                let ranges = conversion_map
-                    .get(&(value_type.to_string(), new_value_type.clone()))
-                    .unwrap();
-
-                let new_value = match ranges.binary_search_by_key(&value, |range| range.source) {
-                    Ok(i) => ranges[i].destination,
-                    Err(0) => value,
-                    Err(i) => ranges[i - 1].get(value).unwrap_or(value),
-                };
-
-                value = new_value;
-                value_type = new_value_type;
-            }
-
-            value
-        })
+        .map(|&seed| almanac.location(seed))
         .min()
         .unwrap()
         .to_string()
 }
 
 fn collapse_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
+    ranges.retain(|range| !range.is_empty());
     ranges.sort_by_key(|range| Reverse(range.start));
 
     let mut final_ranges = Vec::new();
-    final_ranges.push(ranges.pop().unwrap());
+    if let Some(range) = ranges.pop() {
+        final_ranges.push(range);
+    }
 
     // Collapse any overlapping ranges.
     while let Some(range) = ranges.pop() {
@@ -402,7 +513,7 @@ fn collapse_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
             && range.start <= final_ranges.last().unwrap().end
         {
             let mut d = final_ranges.pop().unwrap();
-            d.end = final_ranges.last().unwrap().end.max(range.end);
+            d.end = d.end.max(range.end);
 
             final_ranges.push(d);
         } else {
@@ -560,9 +671,88 @@ pub fn star_two(mut input: impl BufRead) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{star_one, star_two, Almanac, Range};
     use std::io::Cursor;
 
+    const INPUT: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn test_trace() {
+        let almanac = Almanac::parse(INPUT);
+        assert_eq!(
+            almanac.trace(79),
+            vec![
+                ("seed", 79),
+                ("soil", 81),
+                ("fertilizer", 81),
+                ("water", 81),
+                ("light", 74),
+                ("temperature", 78),
+                ("humidity", 78),
+                ("location", 82)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reverse() {
+        let almanac = Almanac::parse(INPUT);
+        let stages = almanac.reverse(Range::new(46, 47));
+        assert_eq!(stages[0], ("location", vec![Range::new(46, 47)]));
+        assert_eq!(stages.last().unwrap().0, "seed");
+        assert!(stages
+            .last()
+            .unwrap()
+            .1
+            .iter()
+            .any(|range| range.start <= 82 && 82 < range.end));
+
+        // Check the reverse mapping against brute force.
+        for (start, end) in [(0, 10), (40, 60), (56, 97), (90, 120)] {
+            let seeds = almanac.seeds_for(Range::new(start, end));
+            for seed in 0..200 {
+                let location = almanac.location(seed);
+                let expected = start <= location && location < end;
+                let found = seeds
+                    .iter()
+                    .any(|range| range.start <= seed && seed < range.end);
+                assert_eq!(expected, found, "seed {seed} -> location {location}");
+            }
+        }
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
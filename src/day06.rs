@@ -1,98 +1,105 @@
 use std::io::BufRead;
 
+/// Counts the hold times that beat `distance` in a race lasting `time`.
+///
+/// Holding for `x` travels `x * (time - x)`, so the winning hold times lie strictly between the
+/// roots of `x^2 - time * x + distance = 0`. The roots are found with an integer square root and
+/// then nudged so that exact integer roots (which only tie the record) are excluded.
+pub fn ways_to_win(time: u64, distance: u128) -> u128 {
+    let t = time as u128;
+    let travelled = |x: u128| x * (t - x);
+
+    // The furthest it is possible to travel is when holding for half the race.
+    if distance >= travelled(t / 2) {
+        return 0;
+    }
+
+    let root = (t * t - 4 * distance).isqrt();
+    let mut lowest = (t - root.min(t)) / 2;
+    while travelled(lowest) <= distance {
+        lowest += 1;
+    }
+    while lowest > 0 && travelled(lowest - 1) > distance {
+        lowest -= 1;
+    }
+
+    // The winning hold times are symmetric around half the race.
+    t - 2 * lowest + 1
+}
+
+fn parse_line(line: &str) -> impl Iterator<Item = &str> {
+    line.split_once(": ").unwrap().1.split_whitespace()
+}
+
 pub fn star_one(input: impl BufRead) -> String {
     let mut lines = input.lines();
 
-    let times = lines
-        .next()
-        .unwrap()
-        .unwrap()
-        .split_once(": ")
-        .unwrap()
-        .1
-        .split_whitespace()
-        .map(|s| s.parse::<isize>().unwrap())
+    let times = parse_line(&lines.next().unwrap().unwrap())
+        .map(|s| s.parse::<u64>().unwrap())
         .collect::<Vec<_>>();
 
-    let distances = lines
-        .next()
-        .unwrap()
-        .unwrap()
-        .split_once(": ")
-        .unwrap()
-        .1
-        .split_whitespace()
-        .map(|s| s.parse::<isize>().unwrap())
+    let distances = parse_line(&lines.next().unwrap().unwrap())
+        .map(|s| s.parse::<u128>().unwrap())
         .collect::<Vec<_>>();
 
     times
         .iter()
         .zip(distances.iter())
-        .map(|(&t, &d)| {
-            let x = (-t as f64 + ((t.pow(2) + 4 * d) as f64).sqrt()) / 2f64;
-
-            // Find all races that it is possible to win
-            (x.ceil() as isize..d)
-                .map(|v| v * (t - v))
-                .filter(|new_d| new_d > &d)
-                .count()
-        })
-        .product::<usize>()
+        .map(|(&t, &d)| ways_to_win(t, d))
+        .product::<u128>()
         .to_string()
 }
 
 pub fn star_two(input: impl BufRead) -> String {
     let mut lines = input.lines();
 
-    let times = {
-        let time = lines
-            .next()
-            .unwrap()
-            .unwrap()
-            .split_once(": ")
-            .unwrap()
-            .1
-            .replace(' ', "");
-
-        vec![time.parse::<isize>().unwrap()]
-    };
-
-    let distances = {
-        let distance = lines
-            .next()
-            .unwrap()
-            .unwrap()
-            .split_once(": ")
-            .unwrap()
-            .1
-            .replace(' ', "");
-        vec![distance.parse::<isize>().unwrap()]
-    };
+    let time = parse_line(&lines.next().unwrap().unwrap())
+        .collect::<String>()
+        .parse::<u64>()
+        .unwrap();
 
-    times
-        .iter()
-        .zip(distances.iter())
-        .map(|(&t, &d)| {
-            // Quadratic equation for solving for x  (-x^2 +Bx + C = 0 where B is time and C is distance)
-            let x1 = (-t as f64 + ((t.pow(2) + 4 * d) as f64).sqrt()) / 2f64;
-
-            let x2 = (-t as f64 - ((t.pow(2) + 4 * d) as f64).sqrt()) / 2f64;
-
-            // Find all races that it is possible to win
-            (x1.ceil() as isize..x2.abs().floor() as isize)
-                .map(|v| v * (t - v))
-                .filter(|new_d| new_d > &d)
-                .count()
-        })
-        .product::<usize>()
-        .to_string()
+    let distance = parse_line(&lines.next().unwrap().unwrap())
+        .collect::<String>()
+        .parse::<u128>()
+        .unwrap();
+
+    ways_to_win(time, distance).to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{star_one, star_two, ways_to_win};
     use std::io::Cursor;
 
+    #[test]
+    fn test_ways_to_win() {
+        assert_eq!(ways_to_win(7, 9), 4);
+        assert_eq!(ways_to_win(15, 40), 8);
+        // Both roots are exact integers (10 and 20) so only tie the record.
+        assert_eq!(ways_to_win(30, 200), 9);
+        assert_eq!(ways_to_win(71530, 940200), 71503);
+        // A perfect square discriminant of zero can only tie the record.
+        assert_eq!(ways_to_win(10, 25), 0);
+        assert_eq!(ways_to_win(0, 0), 0);
+        assert_eq!(ways_to_win(u64::MAX, 0), u64::MAX as u128 - 1);
+    }
+
+    #[test]
+    fn test_ways_to_win_brute_force() {
+        for time in 0..60u64 {
+            for distance in 0..=(time * time / 4 + 2) as u128 {
+                let expected = (0..=time as u128)
+                    .filter(|&x| x * (time as u128 - x) > distance)
+                    .count() as u128;
+                assert_eq!(
+                    ways_to_win(time, distance),
+                    expected,
+                    "time {time} distance {distance}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,279 +1,235 @@
-use itertools::Itertools;
 use std::io::BufRead;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-enum Card {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
+/// A hand category such as "Full house" along with the group sizes it requires, e.g. `[3, 2]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    pub name: String,
+    pattern: Vec<usize>,
 }
 
-static JOKER_REPLACEMENTS: [Card; 13] = [
-    Card::Ace,
-    Card::King,
-    Card::Queen,
-    Card::Jack,
-    Card::Ten,
-    Card::Nine,
-    Card::Eight,
-    Card::Seven,
-    Card::Six,
-    Card::Five,
-    Card::Four,
-    Card::Three,
-    Card::Two,
-];
-
-#[derive(PartialEq, Eq, Debug)]
-enum HandType {
-    FiveOfAKind,
-    FourOfAKind,
-    FullHouse,
-    Three,
-    TwoPair,
-    OnePair,
-    None,
-}
+impl Category {
+    pub fn new(name: &str, mut pattern: Vec<usize>) -> Self {
+        pattern.sort_by(|a, b| b.cmp(a));
+        Category {
+            name: name.to_string(),
+            pattern,
+        }
+    }
 
-impl PartialOrd for HandType {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    /// Returns whether a histogram of card counts (sorted largest first) can satisfy this
+    /// category when `wildcards` cards can stand in for any card.
+    fn matches(&self, counts: &[usize], wildcards: usize) -> bool {
+        let missing = self
+            .pattern
+            .iter()
+            .enumerate()
+            .map(|(i, &needed)| needed.saturating_sub(counts.get(i).copied().unwrap_or(0)))
+            .sum::<usize>();
+        missing <= wildcards
     }
 }
 
-impl Ord for HandType {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self, other) {
-            (HandType::FiveOfAKind, HandType::FiveOfAKind) => std::cmp::Ordering::Equal,
-            (HandType::FiveOfAKind, _) => std::cmp::Ordering::Greater,
-            // Four Of A Kind
-            (HandType::FourOfAKind, HandType::FourOfAKind) => std::cmp::Ordering::Equal,
-            (HandType::FourOfAKind, HandType::FiveOfAKind) => std::cmp::Ordering::Less,
-            (HandType::FourOfAKind, _) => std::cmp::Ordering::Greater,
-            // Full House
-            (HandType::FullHouse, HandType::FullHouse) => std::cmp::Ordering::Equal,
-            (HandType::FullHouse, HandType::FiveOfAKind) => std::cmp::Ordering::Less,
-            (HandType::FullHouse, HandType::FourOfAKind) => std::cmp::Ordering::Less,
-            (HandType::FullHouse, _) => std::cmp::Ordering::Greater,
-            // Three
-            (HandType::Three, HandType::Three) => std::cmp::Ordering::Equal,
-            (HandType::Three, HandType::FiveOfAKind) => std::cmp::Ordering::Less,
-            (HandType::Three, HandType::FourOfAKind) => std::cmp::Ordering::Less,
-            (HandType::Three, HandType::FullHouse) => std::cmp::Ordering::Less,
-            (HandType::Three, _) => std::cmp::Ordering::Greater,
-
-            //Two Pair
-            (HandType::TwoPair, HandType::TwoPair) => std::cmp::Ordering::Equal,
-            (HandType::TwoPair, HandType::FiveOfAKind) => std::cmp::Ordering::Less,
-            (HandType::TwoPair, HandType::FourOfAKind) => std::cmp::Ordering::Less,
-            (HandType::TwoPair, HandType::FullHouse) => std::cmp::Ordering::Less,
-            (HandType::TwoPair, HandType::Three) => std::cmp::Ordering::Less,
-            (HandType::TwoPair, _) => std::cmp::Ordering::Greater,
-
-            // One Pair
-            (HandType::OnePair, HandType::OnePair) => std::cmp::Ordering::Equal,
-            (HandType::OnePair, HandType::FiveOfAKind) => std::cmp::Ordering::Less,
-            (HandType::OnePair, HandType::FourOfAKind) => std::cmp::Ordering::Less,
-            (HandType::OnePair, HandType::FullHouse) => std::cmp::Ordering::Less,
-            (HandType::OnePair, HandType::Three) => std::cmp::Ordering::Less,
-            (HandType::OnePair, HandType::TwoPair) => std::cmp::Ordering::Less,
-            (HandType::OnePair, HandType::None) => std::cmp::Ordering::Greater,
-            // None
-            (HandType::None, HandType::None) => std::cmp::Ordering::Equal,
-            (HandType::None, _) => std::cmp::Ordering::Less,
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hand {
+    /// Index into the rule's categories, higher is stronger.
+    pub category: usize,
+    /// The rank of each card in the order they were dealt, used to break ties.
+    pub ranks: Vec<usize>,
+}
+
+/// The configurable rules of a game of Camel Cards.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    hand_size: usize,
+    /// Cards from weakest to strongest.
+    ranking: Vec<char>,
+    wildcards: Vec<char>,
+    /// Categories from weakest to strongest.
+    categories: Vec<Category>,
+}
+
+impl Rules {
+    pub fn new(hand_size: usize, ranking: &str, categories: Vec<Category>) -> Self {
+        Rules {
+            hand_size,
+            ranking: ranking.chars().collect(),
+            wildcards: Vec::new(),
+            categories,
         }
     }
-}
 
-fn get_hand_type(cards: &[Card]) -> HandType {
-    if cards[0] == cards[4] {
-        HandType::FiveOfAKind
-    } else if cards[0] == cards[3] || cards[1] == cards[4] {
-        HandType::FourOfAKind
-    } else if (cards[0] == cards[2] && cards[3] == cards[4])
-        || (cards[0] == cards[1] && cards[2] == cards[4])
-    {
-        HandType::FullHouse
-    } else if cards[0] == cards[2] || cards[1] == cards[3] || cards[2] == cards[4] {
-        HandType::Three
-    } else if (cards[0] == cards[1] && (cards[2] == cards[3] || cards[3] == cards[4]))
-        || (cards[1] == cards[2] && cards[3] == cards[4])
-    {
-        HandType::TwoPair
-    } else if cards[0] == cards[1]
-        || cards[1] == cards[2]
-        || cards[2] == cards[3]
-        || cards[3] == cards[4]
-    {
-        HandType::OnePair
-    } else {
-        HandType::None
+    pub fn with_wildcards(mut self, wildcards: &str) -> Self {
+        self.wildcards = wildcards.chars().collect();
+        self
+    }
+
+    fn standard_categories() -> Vec<Category> {
+        vec![
+            Category::new("High card", vec![1]),
+            Category::new("One pair", vec![2]),
+            Category::new("Two pair", vec![2, 2]),
+            Category::new("Three of a kind", vec![3]),
+            Category::new("Full house", vec![3, 2]),
+            Category::new("Four of a kind", vec![4]),
+            Category::new("Five of a kind", vec![5]),
+        ]
+    }
+
+    pub fn camel_cards() -> Self {
+        Rules::new(5, "23456789TJQKA", Rules::standard_categories())
+    }
+
+    pub fn camel_cards_with_jokers() -> Self {
+        Rules::new(5, "J23456789TQKA", Rules::standard_categories()).with_wildcards("J")
+    }
+
+    fn rank(&self, card: char) -> usize {
+        self.ranking
+            .iter()
+            .position(|&c| c == card)
+            .unwrap_or_else(|| panic!("Invalid card {card}"))
+    }
+
+    /// Returns the strongest category the cards can make.
+    pub fn classify(&self, cards: &str) -> Option<&Category> {
+        let mut counts = vec![0; self.ranking.len()];
+        let mut wildcards = 0;
+        for card in cards.chars() {
+            if self.wildcards.contains(&card) {
+                wildcards += 1;
+            } else {
+                counts[self.rank(card)] += 1;
+            }
+        }
+        counts.sort_by(|a, b| b.cmp(a));
+
+        self.categories
+            .iter()
+            .rev()
+            .find(|category| category.matches(&counts, wildcards))
+    }
+
+    pub fn hand(&self, cards: &str) -> Hand {
+        assert_eq!(
+            cards.chars().count(),
+            self.hand_size,
+            "Hand {cards} has the wrong number of cards"
+        );
+        let category = self
+            .classify(cards)
+            .unwrap_or_else(|| panic!("Hand {cards} does not match any category"));
+
+        Hand {
+            category: self.categories.iter().position(|c| c == category).unwrap(),
+            ranks: cards.chars().map(|card| self.rank(card)).collect(),
+        }
+    }
+
+    /// Ranks every hand and returns the total of each bid multiplied by its rank.
+    pub fn winnings<'a>(&self, hands: impl IntoIterator<Item = (&'a str, usize)>) -> usize {
+        let mut hands = hands
+            .into_iter()
+            .map(|(cards, bid)| (self.hand(cards), bid))
+            .collect::<Vec<_>>();
+
+        hands.sort_unstable();
+
+        hands
+            .iter()
+            .enumerate()
+            .map(|(i, (_, bid))| (i + 1) * bid)
+            .sum()
     }
 }
 
-pub fn star_one(input: impl BufRead) -> String {
-    let mut hands = input
+fn parse_hands(input: impl BufRead) -> Vec<(String, usize)> {
+    input
         .lines()
         .map(|line| {
             let line = line.unwrap();
-
             let (cards, bid) = line.split_once(' ').unwrap();
-
-            let bid = bid.parse::<usize>().unwrap();
-
-            let cards_input = cards
-                .chars()
-                .map(|c| match c {
-                    '2' => Card::Two,
-                    '3' => Card::Three,
-                    '4' => Card::Four,
-                    '5' => Card::Five,
-                    '6' => Card::Six,
-                    '7' => Card::Seven,
-                    '8' => Card::Eight,
-                    '9' => Card::Nine,
-                    'T' => Card::Ten,
-                    'J' => Card::Jack,
-                    'Q' => Card::Queen,
-                    'K' => Card::King,
-                    'A' => Card::Ace,
-                    _ => panic!("Invalid card"),
-                })
-                .collect::<Vec<_>>();
-
-            let mut cards = cards_input.clone();
-            cards.sort();
-
-            let hand_type = get_hand_type(&cards);
-
-            (hand_type, cards_input, bid)
+            (cards.to_string(), bid.parse::<usize>().unwrap())
         })
-        .collect::<Vec<_>>();
-
-    hands.sort_by(|hand, other| {
-        hand.0.cmp(&other.0).then(
-            hand.1
-                .iter()
-                .zip(other.1.iter())
-                .find_map(|(a, b)| {
-                    let c = a.cmp(b);
-
-                    if c == std::cmp::Ordering::Equal {
-                        None
-                    } else {
-                        Some(c)
-                    }
-                })
-                .unwrap(),
-        )
-    });
-    hands
-        .iter()
-        .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.2)
-        .sum::<usize>()
+        .collect()
+}
+
+pub fn star_one(input: impl BufRead) -> String {
+    let hands = parse_hands(input);
+
+    Rules::camel_cards()
+        .winnings(hands.iter().map(|(cards, bid)| (cards.as_str(), *bid)))
         .to_string()
 }
 
 pub fn star_two(input: impl BufRead) -> String {
-    let mut hands = input
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
+    let hands = parse_hands(input);
 
-            let (cards, bid) = line.split_once(' ').unwrap();
-
-            let bid = bid.parse::<usize>().unwrap();
-
-            let cards_input = cards
-                .chars()
-                .map(|c| match c {
-                    '2' => Card::Two,
-                    '3' => Card::Three,
-                    '4' => Card::Four,
-                    '5' => Card::Five,
-                    '6' => Card::Six,
-                    '7' => Card::Seven,
-                    '8' => Card::Eight,
-                    '9' => Card::Nine,
-                    'T' => Card::Ten,
-                    'J' => Card::Joker,
-                    'Q' => Card::Queen,
-                    'K' => Card::King,
-                    'A' => Card::Ace,
-                    _ => panic!("Invalid card"),
-                })
-                .collect::<Vec<_>>();
-
-            // Find all the jokers
-            let joker_positions = cards_input
-                .iter()
-                .enumerate()
-                .filter_map(|(i, &card)| if card == Card::Joker { Some(i) } else { None })
-                .collect::<Vec<_>>();
-
-            let mut hand_combinations = JOKER_REPLACEMENTS
-                .iter()
-                .combinations_with_replacement(joker_positions.len())
-                .map(|replacements| {
-                    let mut cards = cards_input.clone();
-                    replacements
-                        .iter()
-                        .zip(joker_positions.iter())
-                        .for_each(|(&replacement, &position)| cards[position] = *replacement);
-
-                    cards.sort();
-
-                    get_hand_type(&cards)
-                })
-                .collect::<Vec<_>>();
-
-            hand_combinations.sort();
-
-            (
-                hand_combinations.into_iter().next_back().unwrap(),
-                cards_input,
-                bid,
-            )
-        })
-        .collect::<Vec<_>>();
-
-    hands.sort_by(|hand, other| {
-        hand.0.cmp(&other.0).then(
-            hand.1
-                .iter()
-                .zip(other.1.iter())
-                .find_map(|(a, b)| match a.cmp(b) {
-                    std::cmp::Ordering::Equal => None,
-                    c => Some(c),
-                })
-                .unwrap(),
-        )
-    });
-    hands
-        .iter()
-        .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.2)
-        .sum::<usize>()
+    Rules::camel_cards_with_jokers()
+        .winnings(hands.iter().map(|(cards, bid)| (cards.as_str(), *bid)))
         .to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{star_one, star_two, Category, Rules};
     use std::io::Cursor;
 
+    #[test]
+    fn test_classify() {
+        let rules = Rules::camel_cards();
+        let classify = |cards| rules.classify(cards).unwrap().name.as_str();
+        assert_eq!(classify("AAAAA"), "Five of a kind");
+        assert_eq!(classify("AA8AA"), "Four of a kind");
+        assert_eq!(classify("23332"), "Full house");
+        assert_eq!(classify("TTT98"), "Three of a kind");
+        assert_eq!(classify("23432"), "Two pair");
+        assert_eq!(classify("A23A4"), "One pair");
+        assert_eq!(classify("23456"), "High card");
+        assert_eq!(classify("JJJJJ"), "Five of a kind");
+
+        let rules = Rules::camel_cards_with_jokers();
+        let classify = |cards| rules.classify(cards).unwrap().name.as_str();
+        assert_eq!(classify("QJJQ2"), "Four of a kind");
+        assert_eq!(classify("JJJJJ"), "Five of a kind");
+        assert_eq!(classify("2233J"), "Full house");
+        assert_eq!(classify("2345J"), "One pair");
+        assert_eq!(classify("T55J5"), "Four of a kind");
+    }
+
+    #[test]
+    fn test_tie_break_ranks_jokers_lowest() {
+        let rules = Rules::camel_cards_with_jokers();
+        assert!(rules.hand("JKKK2") < rules.hand("QQQQ2"));
+        assert!(rules.hand("JKKK2") > rules.hand("QKKK2"));
+
+        let rules = Rules::camel_cards();
+        assert!(rules.hand("JKKK2") < rules.hand("QKKK2"));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // A three card game with two wildcards and straights ignored.
+        let rules = Rules::new(
+            3,
+            "123456",
+            vec![
+                Category::new("Nothing", vec![1]),
+                Category::new("Pair", vec![2]),
+                Category::new("Triple", vec![3]),
+            ],
+        )
+        .with_wildcards("16");
+
+        assert_eq!(rules.classify("234").unwrap().name, "Nothing");
+        assert_eq!(rules.classify("212").unwrap().name, "Triple");
+        assert_eq!(rules.classify("235").unwrap().name, "Nothing");
+        assert_eq!(rules.classify("236").unwrap().name, "Pair");
+        assert_eq!(
+            rules.winnings([("236", 10), ("234", 1), ("166", 5)]),
+            1 + 20 + 15
+        );
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,7 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet},
-    io::BufRead,
-};
+use std::{collections::HashMap, io::BufRead};
 
 use num_integer::Integer;
 
@@ -11,51 +8,180 @@ enum Instruction {
     Right,
 }
 
+struct Network<'a> {
+    instructions: Vec<Instruction>,
+    nodes: HashMap<&'a str, (&'a str, &'a str)>,
+}
+
+impl<'a> Network<'a> {
+    fn parse(input: &'a str) -> Self {
+        let mut sections = input.split("\n\n");
+
+        let instructions = sections
+            .next()
+            .unwrap()
+            .trim()
+            .chars()
+            .map(|c| match c {
+                'L' => Instruction::Left,
+                'R' => Instruction::Right,
+                _ => panic!("Unknown instruction"),
+            })
+            .collect();
+
+        let nodes = sections
+            .next()
+            .unwrap()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (from, other) = line.split_once(" = ").unwrap();
+
+                let (left, right) = other
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split_once(", ")
+                    .unwrap();
+                (from, (left, right))
+            })
+            .collect();
+
+        Network {
+            instructions,
+            nodes,
+        }
+    }
+
+    fn next(&self, node: &str, step: usize) -> &'a str {
+        let (left, right) = self.nodes.get(node).unwrap();
+        match self.instructions[step % self.instructions.len()] {
+            Instruction::Left => left,
+            Instruction::Right => right,
+        }
+    }
+
+    /// Follows the path from `start` until a (node, instruction index) state repeats.
+    fn find_cycle(&self, start: &'a str, is_end: impl Fn(&str) -> bool) -> GhostCycle {
+        let mut seen = HashMap::new();
+        let mut hits = Vec::new();
+        let mut current = start;
+        let mut step = 0;
+
+        let tail = loop {
+            let state = (current, step % self.instructions.len());
+            if let Some(&first_seen) = seen.get(&state) {
+                break first_seen;
+            }
+            seen.insert(state, step);
+
+            if is_end(current) {
+                hits.push(step);
+            }
+            current = self.next(current, step);
+            step += 1;
+        };
+
+        let (tail_hits, cycle_hits) = hits.into_iter().partition(|&hit| hit < tail);
+
+        GhostCycle {
+            tail,
+            period: step - tail,
+            tail_hits,
+            cycle_hits,
+        }
+    }
+}
+
+/// The steps at which a single ghost is on an end node.
+#[derive(Debug, PartialEq, Eq)]
+struct GhostCycle {
+    /// Number of steps before the ghost enters its cycle.
+    tail: usize,
+    period: usize,
+    /// End node hits that happen before the cycle is entered.
+    tail_hits: Vec<usize>,
+    /// End node hits within the first pass of the cycle, these repeat every `period` steps.
+    cycle_hits: Vec<usize>,
+}
+
+impl GhostCycle {
+    fn is_hit(&self, step: usize) -> bool {
+        if step < self.tail {
+            self.tail_hits.contains(&step)
+        } else {
+            let offset = (step - self.tail) % self.period;
+            self.cycle_hits.contains(&(self.tail + offset))
+        }
+    }
+}
+
+/// Combines `x ≡ a (mod m)` and `x ≡ b (mod n)` into a single congruence, if one exists.
+fn combine_congruences((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let gcd = m.extended_gcd(&n);
+    if (b - a) % gcd.gcd != 0 {
+        return None;
+    }
+    let lcm = m / gcd.gcd * n;
+    let k = ((b - a) / gcd.gcd * gcd.x).rem_euclid(n / gcd.gcd);
+    Some(((a + m * k).rem_euclid(lcm), lcm))
+}
+
+/// Finds the first step where every ghost is on an end node at the same time.
+fn first_common_step(cycles: &[GhostCycle]) -> Result<usize, String> {
+    let longest_tail = cycles.iter().max_by_key(|cycle| cycle.tail).unwrap();
+
+    // Any common step before all ghosts are in their cycles must be one of the tail hits of the
+    // ghost with the longest tail.
+    if let Some(&step) = longest_tail
+        .tail_hits
+        .iter()
+        .find(|&&step| cycles.iter().all(|cycle| cycle.is_hit(step)))
+    {
+        return Ok(step);
+    }
+
+    // Otherwise all ghosts are cycling so combine every choice of hit with the generalised CRT.
+    let mut congruences = vec![(0, 1)];
+    for cycle in cycles {
+        congruences = congruences
+            .iter()
+            .flat_map(|&congruence| {
+                cycle.cycle_hits.iter().filter_map(move |&hit| {
+                    combine_congruences(congruence, (hit as i128, cycle.period as i128))
+                })
+            })
+            .collect();
+        congruences.sort_unstable();
+        congruences.dedup();
+    }
+
+    let minimum = longest_tail.tail as i128;
+    congruences
+        .into_iter()
+        .map(|(residue, modulus)| {
+            // Find the first step at or after every ghost has entered its cycle.
+            if residue >= minimum {
+                residue
+            } else {
+                residue + (minimum - residue + modulus - 1) / modulus * modulus
+            }
+        })
+        .min()
+        .map(|step| step as usize)
+        .ok_or_else(|| "No step exists where every ghost is on an end node".to_string())
+}
+
 pub fn star_one(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
-    let mut sections = str.split("\n\n");
-
-    let mut instructions = sections
-        .next()
-        .unwrap()
-        .chars()
-        .map(|c| match c {
-            'L' => Instruction::Left,
-            'R' => Instruction::Right,
-            _ => panic!("Unknown instruction"),
-        })
-        .cycle();
-
-    let map = sections
-        .next()
-        .unwrap()
-        .lines()
-        .map(|line| {
-            let (from, other) = line.split_once(" = ").unwrap();
-
-            let (left, right) = other
-                .trim_matches(|c| c == '(' || c == ')')
-                .split_once(", ")
-                .unwrap();
-            (from, (left, right))
-        })
-        .collect::<HashMap<_, _>>();
+    let network = Network::parse(&str);
 
     let mut current = "AAA";
 
     let mut steps = 0;
 
     while current != "ZZZ" {
-        let (left, right) = map.get(current).unwrap();
-
-        let instruction = instructions.next().unwrap();
-
-        current = match instruction {
-            Instruction::Left => *left,
-            Instruction::Right => *right,
-        };
+        current = network.next(current, steps);
         steps += 1;
     }
     steps.to_string()
@@ -65,109 +191,157 @@ pub fn star_two(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
-    let mut sections = str.split("\n\n");
-
-    let instructions = sections
-        .next()
-        .unwrap()
-        .chars()
-        .map(|c| match c {
-            'L' => Instruction::Left,
-            'R' => Instruction::Right,
-            _ => panic!("Unknown instruction"),
-        })
-        .cycle();
-
-    let map = sections
-        .next()
-        .unwrap()
-        .lines()
-        .map(|line| {
-            let (from, other) = line.split_once(" = ").unwrap();
-
-            let (left, right) = other
-                .trim_matches(|c| c == '(' || c == ')')
-                .split_once(", ")
-                .unwrap();
-            (from, (left, right))
-        })
-        .collect::<HashMap<_, _>>();
+    let network = Network::parse(&str);
 
-    let mut current = map
+    let cycles = network
+        .nodes
         .keys()
-        .filter(|k: &&&str| k.ends_with('A'))
-        .copied()
-        .collect::<HashSet<_>>();
-
-    current.shrink_to_fit();
-
-    // Assume that the end node is the only node that the path ends with i.e.
-    // does not go from one *Z to another different *Z
-    let steps = current
-        .iter()
-        .map(|&k| {
-            let mut current = k;
-            let mut instructions = instructions.clone();
-            let mut steps = 0;
-            while !current.ends_with('Z') {
-                let (left, right) = map.get(current).unwrap();
-
-                let instruction = instructions.next().unwrap();
-
-                current = match instruction {
-                    Instruction::Left => *left,
-                    Instruction::Right => *right,
-                };
-                steps += 1;
-            }
-            steps as usize
-        })
+        .filter(|k| k.ends_with('A'))
+        .map(|start| network.find_cycle(start, |node| node.ends_with('Z')))
         .collect::<Vec<_>>();
 
-    steps
-        .into_iter()
-        .reduce(|a, b| a.lcm(&b))
-        .unwrap()
+    first_common_step(&cycles)
+        .unwrap_or_else(|e| panic!("{e}"))
         .to_string()
-
-    //// This was my original solution, but it was too slow
-    //
-    // let end = {
-    //     let mut end = map
-    //         .keys()
-    //         .filter(|k: &&&str| k.ends_with('Z'))
-    //         .copied()
-    //         .collect::<HashSet<_>>();
-
-    //     end.shrink_to_fit();
-
-    //     end
-    // };
-    // let mut steps = 0;
-    // while !current.is_subset(&end) {
-    //     let instruction = instructions.next().unwrap();
-    //     current = current
-    //         .into_iter()
-    //         .map(|k| {
-    //             let (left, right) = map.get(k).unwrap();
-    //             match instruction {
-    //                 Instruction::Left => *left,
-    //                 Instruction::Right => *right,
-    //             }
-    //         })
-    //         .collect::<HashSet<_>>();
-
-    //     steps += 1;
-    // }
-
-    // steps.to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{first_common_step, star_one, star_two, GhostCycle, Network};
+    use crate::test_util::Lcg;
     use std::io::Cursor;
 
+    #[test]
+    fn test_find_cycle() {
+        let input = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+XXX = (XXX, XXX)";
+        let network = Network::parse(input);
+        assert_eq!(
+            network.find_cycle("11A", |node| node.ends_with('Z')),
+            GhostCycle {
+                tail: 1,
+                period: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![2]
+            }
+        );
+    }
+
+    #[test]
+    fn test_first_common_step() {
+        // Offsets that are not equal to the cycle length.
+        let cycles = [
+            GhostCycle {
+                tail: 3,
+                period: 4,
+                tail_hits: vec![],
+                cycle_hits: vec![5],
+            },
+            GhostCycle {
+                tail: 0,
+                period: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![1, 3],
+            },
+        ];
+        // 5, 9, 13 ... against 1, 3, 7, 9, ...
+        assert_eq!(first_common_step(&cycles), Ok(9));
+
+        // A common step before the cycles are entered.
+        let cycles = [
+            GhostCycle {
+                tail: 3,
+                period: 4,
+                tail_hits: vec![2],
+                cycle_hits: vec![5],
+            },
+            GhostCycle {
+                tail: 0,
+                period: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![0],
+            },
+        ];
+        assert_eq!(first_common_step(&cycles), Ok(2));
+
+        // The residues can never line up.
+        let cycles = [
+            GhostCycle {
+                tail: 0,
+                period: 4,
+                tail_hits: vec![],
+                cycle_hits: vec![1],
+            },
+            GhostCycle {
+                tail: 0,
+                period: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![0],
+            },
+        ];
+        assert!(first_common_step(&cycles).is_err());
+    }
+
+    #[test]
+    fn test_first_common_step_brute_force() {
+        // Generate small random networks and compare against stepping every ghost directly.
+        let mut lcg = Lcg::new(12345);
+        let mut random = |n: usize| lcg.below(n);
+
+        for _ in 0..200 {
+            let node_count = 2 + random(8);
+            let name = |i: usize| match i % 3 {
+                0 => format!("{i:02}A"),
+                1 => format!("{i:02}B"),
+                _ => format!("{i:02}Z"),
+            };
+            let instructions = (0..1 + random(4))
+                .map(|_| if random(2) == 0 { 'L' } else { 'R' })
+                .collect::<String>();
+            let nodes = (0..node_count)
+                .map(|i| {
+                    format!(
+                        "{} = ({}, {})",
+                        name(i),
+                        name(random(node_count)),
+                        name(random(node_count))
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let input = format!("{instructions}\n\n{nodes}");
+
+            let network = Network::parse(&input);
+            let starts = (0..node_count).step_by(3).map(name).collect::<Vec<_>>();
+            let cycles = starts
+                .iter()
+                .map(|start| network.find_cycle(start, |node| node.ends_with('Z')))
+                .collect::<Vec<_>>();
+
+            let mut current = starts.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let brute_force = (0..10_000).find(|&step| {
+                let found = current.iter().all(|node| node.ends_with('Z'));
+                current = current
+                    .iter()
+                    .map(|node| network.next(node, step))
+                    .collect();
+                found
+            });
+
+            match brute_force {
+                Some(step) => assert_eq!(first_common_step(&cycles), Ok(step), "{input}"),
+                None => assert!(
+                    first_common_step(&cycles).map_or(true, |step| step >= 10_000),
+                    "{input}"
+                ),
+            }
+        }
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,72 +1,146 @@
 use std::io::BufRead;
 
-pub fn star_one(input: impl BufRead) -> String {
-    input
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            line.split_ascii_whitespace()
-                .map(|value| value.parse::<isize>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .map(|values| {
-            let mut current = vec![values.clone()];
-            while current.last().unwrap().iter().any(|&v| v != 0) {
-                let d = current
-                    .last()
-                    .unwrap()
-                    .windows(2)
-                    .map(|window| {
-                        let (a, b) = (window[0], window[1]);
-                        b - a
-                    })
-                    .collect();
-                current.push(d);
+/// The minimal degree polynomial that generates a sequence, stored in Newton form as the first
+/// value of each row of the difference table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    differences: Vec<i128>,
+}
+
+impl Polynomial {
+    /// Fits a polynomial to `values` where `values[i]` is the value at index `i`. Fails if the
+    /// differences never reach a row of zeros within the length of the sequence, as then any
+    /// polynomial would only be a guess.
+    pub fn fit(values: &[i128]) -> Result<Self, String> {
+        let mut differences = Vec::new();
+        let mut current = values.to_vec();
+
+        while !current.is_empty() {
+            if current.iter().all(|&v| v == 0) {
+                return Ok(Polynomial { differences });
+            }
+            differences.push(current[0]);
+            current = current
+                .windows(2)
+                .map(|window| window[1] - window[0])
+                .collect();
+        }
+
+        Err(format!(
+            "Sequence {values:?} is not a polynomial within its length"
+        ))
+    }
+
+    pub fn degree(&self) -> usize {
+        self.differences.len().saturating_sub(1)
+    }
+
+    /// Returns the value at index `k`, which can be negative or far beyond the sequence, or
+    /// `None` if it or any step in computing it does not fit in an `i128`. The binomial
+    /// coefficients are built up one factor at a time, so this can fail for a large `k` even
+    /// when the value itself would fit.
+    pub fn at(&self, k: i128) -> Option<i128> {
+        // Newton's forward difference formula: sum of Δʲ·C(k, j).
+        let mut binomial = 1i128;
+        let mut value = 0i128;
+        for (j, &difference) in self.differences.iter().enumerate() {
+            if j > 0 {
+                // C(k, j) = C(k, j - 1) * (k - j + 1) / j which is always exact.
+                binomial = binomial.checked_mul(k - j as i128 + 1)? / j as i128;
             }
-            current.iter().map(|v| v.last().unwrap()).sum::<isize>()
-        })
-        .sum::<isize>()
+            value = value.checked_add(difference.checked_mul(binomial)?)?;
+        }
+        Some(value)
+    }
+}
+
+fn parse_sequences(input: impl BufRead) -> impl Iterator<Item = Vec<i128>> {
+    input.lines().map(|line| {
+        line.unwrap()
+            .split_ascii_whitespace()
+            .map(|value| value.parse::<i128>().unwrap())
+            .collect::<Vec<_>>()
+    })
+}
+
+/// The value at index `k` of the polynomial generating `values`.
+fn extrapolate(values: &[i128], k: i128) -> Result<i128, String> {
+    Polynomial::fit(values)?.at(k).ok_or(format!(
+        "Value at {k} of {values:?} does not fit in an i128"
+    ))
+}
+
+pub fn star_one(input: impl BufRead) -> String {
+    parse_sequences(input)
+        .map(|values| extrapolate(&values, values.len() as i128))
+        .sum::<Result<i128, _>>()
+        .unwrap_or_else(|e| panic!("{e}"))
         .to_string()
 }
 
 pub fn star_two(input: impl BufRead) -> String {
-    input
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            line.split_ascii_whitespace()
-                .map(|value| value.parse::<isize>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .map(|values| {
-            let mut current = vec![values.clone()];
-            while current.last().unwrap().iter().any(|&v| v != 0) {
-                let d = current
-                    .last()
-                    .unwrap()
-                    .windows(2)
-                    .map(|window| {
-                        let (a, b) = (window[0], window[1]);
-                        b - a
-                    })
-                    .collect();
-                current.push(d);
-            }
-            current
-                .iter()
-                .zip([1, -1].iter().cycle())
-                .map(|(v, m)| m * v.first().unwrap())
-                .sum::<isize>()
-        })
-        .sum::<isize>()
+    parse_sequences(input)
+        .map(|values| extrapolate(&values, -1))
+        .sum::<Result<i128, _>>()
+        .unwrap_or_else(|e| panic!("{e}"))
         .to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{extrapolate, star_one, star_two, Polynomial};
     use std::io::Cursor;
 
+    #[test]
+    fn test_polynomial() {
+        let polynomial = Polynomial::fit(&[10, 13, 16, 21, 30, 45]).unwrap();
+        assert_eq!(polynomial.degree(), 3);
+        assert_eq!(polynomial.at(6), Some(68));
+        assert_eq!(polynomial.at(-1), Some(5));
+
+        // n^2 + 1
+        let polynomial = Polynomial::fit(&[1, 2, 5, 10, 17]).unwrap();
+        assert_eq!(polynomial.degree(), 2);
+        assert_eq!(polynomial.at(-7), Some(50));
+        assert_eq!(
+            polynomial.at(1_000_000_000),
+            Some(1_000_000_000_000_000_001)
+        );
+        assert_eq!(polynomial.at(i128::MAX), None);
+
+        let polynomial = Polynomial::fit(&[4, 4, 4]).unwrap();
+        assert_eq!(polynomial.degree(), 0);
+        assert_eq!(polynomial.at(-100), Some(4));
+
+        let polynomial = Polynomial::fit(&[0, 0]).unwrap();
+        assert_eq!(polynomial.at(5), Some(0));
+    }
+
+    #[test]
+    fn test_polynomial_matches_cubic() {
+        let cubic = |k: i128| 2 * k * k * k - 5 * k * k + 3 * k - 7;
+        let values = (0..6).map(cubic).collect::<Vec<_>>();
+        let polynomial = Polynomial::fit(&values).unwrap();
+        assert_eq!(polynomial.degree(), 3);
+        for k in -50..50 {
+            assert_eq!(polynomial.at(k), Some(cubic(k)));
+        }
+    }
+
+    #[test]
+    fn test_not_polynomial() {
+        assert!(Polynomial::fit(&[1, 2, 4, 8, 16]).is_err());
+        assert!(Polynomial::fit(&[3]).is_err());
+        assert!(Polynomial::fit(&[]).is_err());
+        assert!(extrapolate(&[1, 2, 5], i128::MAX).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Sequence [1, 2, 4, 8, 16] is not a polynomial")]
+    fn test_star_one_not_polynomial() {
+        star_one(Cursor::new(b"0 3 6 9 12 15\n1 2 4 8 16"));
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,7 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    io::BufRead,
-};
+use std::{collections::HashSet, io::BufRead};
 
 /*
 
@@ -17,7 +14,7 @@ use std::{
 */
 
 #[derive(PartialEq, Clone, Copy, Debug)]
-enum Position {
+pub enum Position {
     Empty,
     Vertical,
     Horizontal,
@@ -28,340 +25,441 @@ enum Position {
     Start,
 }
 
-fn get_count_to_edge(
-    map: &HashMap<(isize, isize), Position>,
-    path: &HashSet<(isize, isize)>,
-    pos: &(isize, isize),
-) -> usize {
-    let mut crossing_count = 0;
-
-    let mut last_bend = None;
-
-    for i in 0..=pos.0 {
-        if path.contains(&(i, pos.1)) {
-            let pos_type = map.get(&(i, pos.1)).unwrap();
-
-            let pos_type = if matches!(pos_type, Position::Start) {
-                let above = matches!(
-                    map.get(&(i - 1, pos.1)),
-                    Some(Position::Vertical)
-                        | Some(Position::SouthEast)
-                        | Some(Position::SouthWest)
-                );
-
-                let left = matches!(
-                    map.get(&(i, pos.1 - 1)),
-                    Some(Position::Horizontal)
-                        | Some(Position::NorthEast)
-                        | Some(Position::SouthEast)
-                );
-                let right = matches!(
-                    map.get(&(i, pos.1 + 1)),
-                    Some(Position::Horizontal)
-                        | Some(Position::SouthWest)
-                        | Some(Position::NorthWest)
-                );
-
-                let down = matches!(
-                    map.get(&(i + 1, pos.1)),
-                    Some(Position::Vertical)
-                        | Some(Position::NorthWest)
-                        | Some(Position::NorthEast)
-                );
-
-                match (above, left, right, down) {
-                    (true, true, true, true) => panic!(),
-                    (true, true, true, false) => panic!(),
-                    (true, true, false, true) => panic!(),
-                    (true, true, false, false) => Position::NorthWest,
-                    (true, false, true, true) => panic!(),
-                    (true, false, true, false) => Position::NorthEast,
-                    (true, false, false, true) => Position::Vertical,
-                    (true, false, false, false) => panic!(),
-                    (false, true, true, true) => panic!(),
-                    (false, true, true, false) => Position::Horizontal,
-                    (false, true, false, true) => Position::SouthWest,
-                    (false, true, false, false) => panic!(),
-                    (false, false, true, true) => Position::SouthEast,
-                    (false, false, true, false) => panic!(),
-                    (false, false, false, true) => panic!(),
-                    (false, false, false, false) => panic!(),
-                }
-            } else {
-                *pos_type
-            };
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
 
-            if pos == &(8, 12) {
-                println!("({} {}) {} - {:?}", i, pos.1, crossing_count, pos_type);
-            }
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn step(&self, (y, x): (isize, isize)) -> (isize, isize) {
+        match self {
+            Direction::North => (y - 1, x),
+            Direction::East => (y, x + 1),
+            Direction::South => (y + 1, x),
+            Direction::West => (y, x - 1),
+        }
+    }
 
-            match pos_type {
-                Position::Empty => {}
-                Position::Vertical => {}
-                Position::Horizontal => {
-                    crossing_count += 1;
-                }
-                Position::NorthEast => {
-                    if let Some(last_bend) = last_bend.take() {
-                        if last_bend == Position::SouthWest {
-                            crossing_count += 1;
-                        }
-                    } else {
-                        panic!("we should have seen a bend")
-                    }
-                }
-                Position::NorthWest => {
-                    if let Some(last_bend) = last_bend.take() {
-                        if last_bend == Position::SouthEast {
-                            crossing_count += 1;
-                        }
-                    } else {
-                        panic!("we should have seen a bend")
-                    }
-                }
-                Position::SouthWest => {
-                    if last_bend.is_none() {
-                        last_bend = Some(Position::SouthWest);
-                    } else {
-                        panic!("we should not have seen a bend")
-                    }
-                }
-                Position::SouthEast => {
-                    if last_bend.is_none() {
-                        last_bend = Some(Position::SouthEast);
-                    } else {
-                        panic!("we should not have seen a bend")
-                    }
-                }
-                Position::Start => {
-                    panic!()
-                    // Might have to look and see what type it is
-                }
-            }
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
         }
     }
-    crossing_count
 }
 
-pub fn star_one(input: impl BufRead) -> String {
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            let line = line.unwrap();
-            line.chars()
-                .enumerate()
-                .map(move |(x, c)| {
-                    let pos = match c {
-                        '.' => Position::Empty,
-                        '|' => Position::Vertical,
-                        '-' => Position::Horizontal,
-                        'L' => Position::NorthEast,
-                        'J' => Position::NorthWest,
-                        '7' => Position::SouthWest,
-                        'F' => Position::SouthEast,
-                        'S' => Position::Start,
-
-                        _ => panic!("Unknown character"),
-                    };
-                    ((y as isize, x as isize), pos)
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<HashMap<_, _>>();
+impl Position {
+    fn from_char(c: char) -> Position {
+        match c {
+            '.' => Position::Empty,
+            '|' => Position::Vertical,
+            '-' => Position::Horizontal,
+            'L' => Position::NorthEast,
+            'J' => Position::NorthWest,
+            '7' => Position::SouthWest,
+            'F' => Position::SouthEast,
+            'S' => Position::Start,
+            x => panic!("Unknown character: {x}"),
+        }
+    }
 
-    let start = map
-        .iter()
-        .find_map(|(&pos, value)| {
-            if value == &Position::Start {
-                Some(pos)
-            } else {
-                None
-            }
-        })
-        .unwrap();
+    fn connections(&self) -> Option<[Direction; 2]> {
+        match self {
+            Position::Vertical => Some([Direction::North, Direction::South]),
+            Position::Horizontal => Some([Direction::East, Direction::West]),
+            Position::NorthEast => Some([Direction::North, Direction::East]),
+            Position::NorthWest => Some([Direction::North, Direction::West]),
+            Position::SouthWest => Some([Direction::South, Direction::West]),
+            Position::SouthEast => Some([Direction::South, Direction::East]),
+            Position::Empty | Position::Start => None,
+        }
+    }
 
-    let mut stack = VecDeque::new();
+    fn connects(&self, direction: Direction) -> bool {
+        self.connections()
+            .map(|connections| connections.contains(&direction))
+            .unwrap_or(false)
+    }
 
-    stack.push_back((start, 0, HashSet::new()));
+    fn from_connections(connections: &[Direction]) -> Option<Position> {
+        [
+            Position::Vertical,
+            Position::Horizontal,
+            Position::NorthEast,
+            Position::NorthWest,
+            Position::SouthWest,
+            Position::SouthEast,
+        ]
+        .into_iter()
+        .find(|pos| connections.iter().all(|&direction| pos.connects(direction)))
+    }
+}
 
-    let mut circ = Vec::new();
+pub struct Maze {
+    /// The tiles of the maze, with the start replaced by the pipe it sits on.
+    tiles: Vec<Vec<Position>>,
+    start: (isize, isize),
+}
 
-    while let Some(((y, x), steps, mut seen)) = stack.pop_back() {
-        // println!("{:?} {}", (y, x), steps);
-        if seen.contains(&(y, x)) {
-            circ.push(steps);
-            continue;
+impl Maze {
+    pub fn parse(input: impl BufRead) -> Self {
+        let tiles = input
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().map(Position::from_char).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let start = tiles
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| {
+                row.iter()
+                    .position(|pos| pos == &Position::Start)
+                    .map(|x| (y as isize, x as isize))
+            })
+            .expect("No start position");
+
+        let mut maze = Maze { tiles, start };
+        let shape = maze.start_shape();
+        maze.tiles[start.0 as usize][start.1 as usize] = shape;
+        maze
+    }
+
+    pub fn get(&self, (y, x): (isize, isize)) -> Position {
+        if y < 0 || x < 0 {
+            return Position::Empty;
         }
-        seen.insert((y, x));
-        if let Some(pos) = map.get(&(y, x)) {
-            match pos {
-                Position::Empty => {}
-                Position::Vertical => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                }
-                Position::Horizontal => {
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::NorthEast => {
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                }
-                Position::NorthWest => {
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::SouthWest => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::SouthEast => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                }
-                Position::Start => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
+        self.tiles
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(Position::Empty)
+    }
+
+    /// Works out the pipe under the start. Stray pipes next to the start may also point into
+    /// it, so each pair of neighbours that connect to it is tried until one closes the loop.
+    fn start_shape(&self) -> Position {
+        let connections = Direction::ALL
+            .into_iter()
+            .filter(|direction| {
+                self.get(direction.step(self.start))
+                    .connects(direction.opposite())
+            })
+            .collect::<Vec<_>>();
+
+        connections
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &a)| connections[i + 1..].iter().map(move |&b| [a, b]))
+            .filter_map(|pair| Position::from_connections(&pair))
+            .find(|&shape| self.follow(shape).is_some())
+            .unwrap_or_else(|| panic!("Start is not on a loop, it connects to {connections:?}"))
+    }
+
+    /// Walks from the start as if it were the given pipe, returning every tile passed in order
+    /// if the walk comes back into the start through the pipe's other end.
+    fn follow(&self, shape: Position) -> Option<Vec<(isize, isize)>> {
+        let [mut direction, end] = shape.connections()?;
+        let mut path = vec![self.start];
+        let mut current = direction.step(self.start);
+
+        while current != self.start {
+            path.push(current);
+            let came_from = direction.opposite();
+            let connections = self.get(current).connections()?;
+            if !connections.contains(&came_from) {
+                return None;
             }
+            direction = connections.into_iter().find(|&d| d != came_from).unwrap();
+            current = direction.step(current);
         }
+
+        (direction.opposite() == end).then_some(path)
     }
 
-    circ.iter().max().map(|x| x / 2).unwrap().to_string()
+    /// Walks the loop from the start, returning every tile on it in order.
+    pub fn trace_loop(&self) -> Vec<(isize, isize)> {
+        self.follow(self.get(self.start))
+            .expect("The start is always on a loop after parsing")
+    }
 }
 
-pub fn star_two(input: impl BufRead) -> String {
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            let line = line.unwrap();
-            line.chars()
-                .enumerate()
-                .map(move |(x, c)| {
-                    let pos = match c {
-                        '.' => Position::Empty,
-                        '|' => Position::Vertical,
-                        '-' => Position::Horizontal,
-                        'L' => Position::NorthEast,
-                        'J' => Position::NorthWest,
-                        '7' => Position::SouthWest,
-                        'F' => Position::SouthEast,
-                        'S' => Position::Start,
-
-                        x => panic!("Unknown character: {x}"),
-                    };
-                    ((y as isize, x as isize), pos)
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<HashMap<_, _>>();
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Tile {
+    Loop,
+    Inside,
+    Outside,
+}
 
-    let start = map
+/// Counts the tiles enclosed by the loop. The shoelace formula gives the area of the polygon
+/// through the tile centres, and Pick's theorem turns that into the number of interior points.
+pub fn enclosed_tiles(path: &[(isize, isize)]) -> usize {
+    let twice_area = path
         .iter()
-        .find_map(|(&pos, value)| {
-            if value == &Position::Start {
-                Some(pos)
-            } else {
-                None
-            }
-        })
-        .unwrap();
+        .zip(path.iter().cycle().skip(1))
+        .map(|(&(y1, x1), &(y2, x2))| x1 * y2 - x2 * y1)
+        .sum::<isize>()
+        .unsigned_abs();
 
-    let mut stack = VecDeque::new();
+    // A = I + B / 2 - 1
+    (twice_area + 2 - path.len()) / 2
+}
 
-    stack.push_back((start, 0, HashSet::new()));
+impl Maze {
+    /// Marks every tile as part of the loop, inside it or outside it.
+    ///
+    /// Scanning each row from the left, we cross the loop every time we pass a loop tile that
+    /// connects north, so the parity of those crossings tells us if we are inside.
+    pub fn classify(&self, path: &[(isize, isize)]) -> Vec<Vec<Tile>> {
+        let path = path.iter().collect::<HashSet<_>>();
+
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                let mut inside = false;
+                row.iter()
+                    .enumerate()
+                    .map(|(x, tile)| {
+                        if path.contains(&(y as isize, x as isize)) {
+                            if tile.connects(Direction::North) {
+                                inside = !inside;
+                            }
+                            Tile::Loop
+                        } else if inside {
+                            Tile::Inside
+                        } else {
+                            Tile::Outside
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
 
-    let mut circ = Vec::new();
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Style {
+    Plain,
+    Ansi,
+}
 
-    while let Some(((y, x), steps, mut seen)) = stack.pop_back() {
-        // println!("{:?} {}", (y, x), steps);
-        if seen.contains(&(y, x)) {
-            circ.push((steps, seen));
-            continue;
+impl Position {
+    fn glyph(&self, heavy: bool) -> char {
+        match (self, heavy) {
+            (Position::Vertical, false) => '│',
+            (Position::Horizontal, false) => '─',
+            (Position::NorthEast, false) => '└',
+            (Position::NorthWest, false) => '┘',
+            (Position::SouthWest, false) => '┐',
+            (Position::SouthEast, false) => '┌',
+            (Position::Vertical, true) => '┃',
+            (Position::Horizontal, true) => '━',
+            (Position::NorthEast, true) => '┗',
+            (Position::NorthWest, true) => '┛',
+            (Position::SouthWest, true) => '┓',
+            (Position::SouthEast, true) => '┏',
+            (Position::Empty, _) => ' ',
+            (Position::Start, _) => 'S',
         }
-        seen.insert((y, x));
-        if let Some(pos) = map.get(&(y, x)) {
-            match pos {
-                Position::Empty => {}
-                Position::Vertical => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                }
-                Position::Horizontal => {
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::NorthEast => {
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                }
-                Position::NorthWest => {
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::SouthWest => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
-                }
-                Position::SouthEast => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                }
-                Position::Start => {
-                    stack.push_back(((y + 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y - 1, x), steps + 1, seen.clone()));
-                    stack.push_back(((y, x + 1), steps + 1, seen.clone()));
-                    stack.push_back(((y, x - 1), steps + 1, seen.clone()));
+    }
+}
+
+impl Maze {
+    /// Draws the maze with box drawing characters. The main loop is drawn with heavy lines and
+    /// the tiles it encloses are shaded, either with a block character or an ANSI background.
+    pub fn render(&self, style: Style) -> String {
+        let tiles = self.classify(&self.trace_loop());
+
+        let mut output = String::new();
+        for (y, row) in self.tiles.iter().enumerate() {
+            for (x, pos) in row.iter().enumerate() {
+                let is_start = (y as isize, x as isize) == self.start;
+                match (style, tiles[y][x]) {
+                    (Style::Plain, Tile::Loop) => output.push(pos.glyph(true)),
+                    (Style::Plain, Tile::Inside) => output.push('▒'),
+                    (Style::Plain, Tile::Outside) => output.push(pos.glyph(false)),
+                    (Style::Ansi, Tile::Loop) => {
+                        // The start is highlighted in red, the rest of the loop in yellow.
+                        let colour = if is_start { 31 } else { 33 };
+                        output.push_str(&format!("\x1b[1;{colour}m{}\x1b[0m", pos.glyph(true)));
+                    }
+                    (Style::Ansi, Tile::Inside) => {
+                        output.push_str(&format!("\x1b[42m{}\x1b[0m", pos.glyph(false)));
+                    }
+                    (Style::Ansi, Tile::Outside) => {
+                        output.push_str(&format!("\x1b[2m{}\x1b[0m", pos.glyph(false)));
+                    }
                 }
             }
+            output.push('\n');
         }
+        output
     }
+}
 
-    let path = circ.iter().max_by_key(|k| k.0).unwrap();
-    let max_y = map.keys().map(|(y, _)| y).max().unwrap();
-    let max_x = map.keys().map(|(_, x)| x).max().unwrap();
+pub fn star_one(input: impl BufRead) -> String {
+    let maze = Maze::parse(input);
 
-    for i in 0..=*max_y {
-        for j in 0..=*max_x {
-            if path.1.contains(&(i, j)) {
-                print!("x");
-            } else {
-                print!(" ");
-            }
-        }
-        println!()
-    }
+    (maze.trace_loop().len() / 2).to_string()
+}
 
-    let mut total = 0;
-    for i in 0..=*max_y {
-        for j in 0..=*max_x {
-            if path.1.contains(&(i, j)) {
-                print!("x");
-                continue;
-            }
-            let count = get_count_to_edge(&map, &path.1, &(i, j));
-            if count % 2 == 1 {
-                print!("{count}");
-                total += 1;
-            } else {
-                print!("{count}");
-            }
-        }
-        println!()
-    }
-    total.to_string()
+pub fn star_two(input: impl BufRead) -> String {
+    let maze = Maze::parse(input);
+
+    enclosed_tiles(&maze.trace_loop()).to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{enclosed_tiles, star_one, star_two, Maze, Position, Style, Tile};
     use std::io::Cursor;
 
+    #[test]
+    fn test_trace_loop() {
+        let maze = Maze::parse(Cursor::new(
+            b"-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF",
+        ));
+        assert_eq!(maze.get(maze.start), Position::SouthEast);
+        let path = maze.trace_loop();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path[0], (1, 1));
+        // Every tile on the loop is next to the one after it.
+        for (a, b) in path.iter().zip(path.iter().cycle().skip(1)) {
+            assert_eq!((a.0 - b.0).abs() + (a.1 - b.1).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn test_start_shapes() {
+        for (input, shape) in [
+            ("F7\nS|\nLJ", Position::Vertical),
+            ("FS7\nL-J", Position::Horizontal),
+            ("F7\nSJ", Position::NorthEast),
+            ("F7\nLS", Position::NorthWest),
+            ("FS\nLJ", Position::SouthWest),
+            ("S7\nLJ", Position::SouthEast),
+        ] {
+            let maze = Maze::parse(Cursor::new(input));
+            assert_eq!(maze.get(maze.start), shape);
+        }
+    }
+
+    #[test]
+    fn test_stray_pipe_into_start() {
+        // The `-` west of the start also points into it, but isn't part of the loop.
+        let input = "..|..\n.F-7.\n-S.|.\n.L-J.\n.....";
+        let maze = Maze::parse(Cursor::new(input));
+        assert_eq!(maze.get(maze.start), Position::Vertical);
+        assert_eq!(star_one(Cursor::new(input)), "4");
+        assert_eq!(star_two(Cursor::new(input)), "1");
+    }
+
+    #[test]
+    fn test_classify_matches_enclosed_tiles() {
+        let input = ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...";
+        let maze = Maze::parse(Cursor::new(input));
+        let path = maze.trace_loop();
+        let tiles = maze.classify(&path);
+
+        let count = |kind| tiles.iter().flatten().filter(|&&t| t == kind).count();
+        assert_eq!(count(Tile::Inside), 8);
+        assert_eq!(count(Tile::Loop), path.len());
+        assert_eq!(enclosed_tiles(&path), 8);
+
+        // Moving the start to any other tile of the loop must give the same answer.
+        let original = input.replace('S', "F");
+        for &(y, x) in &path {
+            let moved = original
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    line.chars()
+                        .enumerate()
+                        .map(|(j, c)| {
+                            if (i as isize, j as isize) == (y, x) {
+                                'S'
+                            } else {
+                                c
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let maze = Maze::parse(Cursor::new(moved));
+            let path = maze.trace_loop();
+            assert_eq!(enclosed_tiles(&path), 8);
+            let tiles = maze.classify(&path);
+            assert_eq!(
+                tiles
+                    .iter()
+                    .flatten()
+                    .filter(|&&t| t == Tile::Inside)
+                    .count(),
+                8
+            );
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let maze = Maze::parse(Cursor::new(
+            b"..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........",
+        ));
+        assert_eq!(
+            maze.render(Style::Plain),
+            "          
+ ┏━━━━━━┓ 
+ ┃┏━━━━┓┃ 
+ ┃┃    ┃┃ 
+ ┃┃    ┃┃ 
+ ┃┗━┓┏━┛┃ 
+ ┃▒▒┃┃▒▒┃ 
+ ┗━━┛┗━━┛ 
+          
+"
+        );
+
+        let rendered = maze.render(Style::Ansi);
+        assert_eq!(rendered.matches("\x1b[42m").count(), 4);
+        assert_eq!(rendered.matches("\x1b[1;31m┏").count(), 1);
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,167 +1,107 @@
-use itertools::Itertools;
 use std::io::BufRead;
 
-pub fn star_one(input: impl BufRead) -> String {
-    let mut galaxy_positions = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            let line = line.unwrap();
-            line.chars()
-                .enumerate()
-                .filter_map(move |(x, c)| {
-                    if c == '#' {
-                        Some((y as isize, x as isize))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-
-    let mut galaxy_positions = {
-        // Expand rows
-        // First sort the positions by y
-        galaxy_positions.sort_by_key(|&(y, _)| y);
-
-        let mut new_positions = Vec::with_capacity(galaxy_positions.len());
-
-        // Add the first one as we know that it does not need expanding. (Might get a bit confusing 😅)
-        new_positions.push(galaxy_positions[0]);
-
-        let mut current_expansion = 0;
-
-        for i in 1..galaxy_positions.len() {
-            let galaxy = galaxy_positions[i];
-            let last_galaxy = galaxy_positions[i - 1];
-            if galaxy.0 - last_galaxy.0 > 1 {
-                let expansion = galaxy.0 - last_galaxy.0 - 1;
-                current_expansion += expansion;
-            }
-            // Expand the number of rows empty * 2
-
-            new_positions.push((galaxy.0 + current_expansion, galaxy.1));
-        }
-
-        new_positions
-    };
-
-    let galaxy_positions = {
-        // Expand rows
-        // First sort the positions by y (since we `pop` form the end we do it in reverse order)
-        galaxy_positions.sort_by_key(|&(_, x)| x);
-
-        let mut new_positions = Vec::with_capacity(galaxy_positions.len());
-
-        // Add the first one as we know that it does not need expanding. (Might get a bit confusing 😅)
-        new_positions.push(galaxy_positions[0]);
-
-        let mut current_expansion = 0;
-
-        for i in 1..galaxy_positions.len() {
-            let galaxy = galaxy_positions[i];
-            let last_galaxy = galaxy_positions[i - 1];
-            if galaxy.1 - last_galaxy.1 > 1 {
-                // Expand the number of rows empty * 2
-                let expansion = galaxy.1 - last_galaxy.1 - 1;
-                current_expansion += expansion;
-            }
-
-            new_positions.push((galaxy.0, galaxy.1 + current_expansion));
-        }
-
-        new_positions
-    };
+/// Galaxy positions after the empty rows and columns have been expanded.
+#[derive(Debug, Clone)]
+pub struct Universe {
+    /// In the order they appear in the image, reading left to right and top to bottom.
+    galaxies: Vec<(isize, isize)>,
+}
 
-    galaxy_positions
-        .into_iter()
-        .combinations(2)
-        .map(|pair| (pair[0].1 - pair[1].1).abs() + (pair[0].0 - pair[1].0).abs())
-        .sum::<isize>()
-        .to_string()
+/// Maps each coordinate so that every empty line before it counts as `factor` lines.
+fn expand_axis(coordinates: impl Iterator<Item = isize>, factor: isize) -> Vec<isize> {
+    let coordinates = coordinates.collect::<Vec<_>>();
+    let mut occupied = coordinates.clone();
+    occupied.sort_unstable();
+    occupied.dedup();
+
+    coordinates
+        .iter()
+        .map(|&c| {
+            let empty_before = c - occupied.partition_point(|&o| o < c) as isize;
+            c + empty_before * (factor - 1)
+        })
+        .collect()
 }
 
-pub fn star_two(input: impl BufRead) -> String {
-    let factor = 1000000;
-    let mut galaxy_positions = input
-        .lines()
+/// Sum of `|a - b|` over every pair, by sorting and using prefix sums.
+fn pairwise_distance_sum(mut values: Vec<isize>) -> isize {
+    values.sort_unstable();
+    let mut prefix = 0;
+    values
+        .iter()
         .enumerate()
-        .flat_map(|(y, line)| {
-            let line = line.unwrap();
-            line.chars()
-                .enumerate()
-                .filter_map(move |(x, c)| {
-                    if c == '#' {
-                        Some((y as isize, x as isize))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
+        .map(|(i, &value)| {
+            let total = value * i as isize - prefix;
+            prefix += value;
+            total
         })
-        .collect::<Vec<_>>();
-
-    let mut galaxy_positions = {
-        // Expand rows
-        // First sort the positions by y
-        galaxy_positions.sort_by_key(|&(y, _)| y);
-
-        let mut new_positions = Vec::with_capacity(galaxy_positions.len());
-
-        // Add the first one as we know that it does not need expanding. (Might get a bit confusing 😅)
-        new_positions.push(galaxy_positions[0]);
-
-        let mut current_expansion = 0;
-
-        for i in 1..galaxy_positions.len() {
-            let galaxy = galaxy_positions[i];
-            let last_galaxy = galaxy_positions[i - 1];
-            if galaxy.0 - last_galaxy.0 > 1 {
-                let expansion = (galaxy.0 - last_galaxy.0 - 1) * (factor - 1);
-                current_expansion += expansion;
-            }
-            // Expand the number of rows empty * 2
+        .sum()
+}
 
-            new_positions.push((galaxy.0 + current_expansion, galaxy.1));
+impl Universe {
+    pub fn parse(input: impl BufRead, factor: isize) -> Self {
+        let galaxies = input
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                let line = line.unwrap();
+                line.chars()
+                    .enumerate()
+                    .filter_map(move |(x, c)| {
+                        if c == '#' {
+                            Some((y as isize, x as isize))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let ys = expand_axis(galaxies.iter().map(|&(y, _)| y), factor);
+        let xs = expand_axis(galaxies.iter().map(|&(_, x)| x), factor);
+
+        Universe {
+            galaxies: ys.into_iter().zip(xs).collect(),
         }
+    }
 
-        new_positions
-    };
-
-    let galaxy_positions = {
-        // Expand rows
-        // First sort the positions by y (since we `pop` form the end we do it in reverse order)
-        galaxy_positions.sort_by_key(|&(_, x)| x);
-
-        let mut new_positions = Vec::with_capacity(galaxy_positions.len());
+    pub fn len(&self) -> usize {
+        self.galaxies.len()
+    }
 
-        // Add the first one as we know that it does not need expanding. (Might get a bit confusing 😅)
-        new_positions.push(galaxy_positions[0]);
+    pub fn is_empty(&self) -> bool {
+        self.galaxies.is_empty()
+    }
 
-        let mut current_expansion = 0;
+    /// Manhattan distance between the `a`th and `b`th galaxy (zero indexed).
+    pub fn distance(&self, a: usize, b: usize) -> isize {
+        let (a, b) = (self.galaxies[a], self.galaxies[b]);
+        (a.0 - b.0).abs() + (a.1 - b.1).abs()
+    }
 
-        for i in 1..galaxy_positions.len() {
-            let galaxy = galaxy_positions[i];
-            let last_galaxy = galaxy_positions[i - 1];
-            if galaxy.1 - last_galaxy.1 > 1 {
-                // Expand the number of rows empty * 2
-                let expansion = (galaxy.1 - last_galaxy.1 - 1) * (factor - 1);
-                current_expansion += expansion;
-            }
+    /// Returns the closest other galaxy to the `a`th galaxy along with its distance.
+    pub fn nearest(&self, a: usize) -> Option<(usize, isize)> {
+        (0..self.galaxies.len())
+            .filter(|&b| b != a)
+            .map(|b| (b, self.distance(a, b)))
+            .min_by_key(|&(_, distance)| distance)
+    }
 
-            new_positions.push((galaxy.0, galaxy.1 + current_expansion));
-        }
+    /// Sum of the distances between every pair of galaxies. As the distance is Manhattan each
+    /// axis can be summed on its own.
+    pub fn total_distance(&self) -> isize {
+        pairwise_distance_sum(self.galaxies.iter().map(|&(y, _)| y).collect())
+            + pairwise_distance_sum(self.galaxies.iter().map(|&(_, x)| x).collect())
+    }
+}
 
-        new_positions
-    };
+pub fn star_one(input: impl BufRead) -> String {
+    Universe::parse(input, 2).total_distance().to_string()
+}
 
-    galaxy_positions
-        .into_iter()
-        .combinations(2)
-        .map(|pair| (pair[0].1 - pair[1].1).abs() + (pair[0].0 - pair[1].0).abs())
-        .sum::<isize>()
-        .to_string()
+pub fn star_two(input: impl BufRead) -> String {
+    Universe::parse(input, 1000000).total_distance().to_string()
 }
 
 #[cfg(test)]
@@ -198,4 +138,46 @@ mod tests {
 
     #[test]
     fn test_star_two() {}
+
+    const INPUT: &[u8] = b"...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+    #[test]
+    fn test_expansion_factor() {
+        assert_eq!(Universe::parse(Cursor::new(INPUT), 2).total_distance(), 374);
+        assert_eq!(
+            Universe::parse(Cursor::new(INPUT), 10).total_distance(),
+            1030
+        );
+        assert_eq!(
+            Universe::parse(Cursor::new(INPUT), 100).total_distance(),
+            8410
+        );
+    }
+
+    #[test]
+    fn test_distance_queries() {
+        let universe = Universe::parse(Cursor::new(INPUT), 2);
+        assert_eq!(universe.len(), 9);
+        assert_eq!(universe.distance(4, 8), 9);
+        assert_eq!(universe.distance(0, 6), 15);
+        assert_eq!(universe.distance(2, 5), 17);
+        assert_eq!(universe.distance(7, 8), 5);
+        assert_eq!(universe.nearest(7), Some((8, 5)));
+
+        // Check the prefix sum against summing every pair.
+        let total = (0..universe.len())
+            .flat_map(|a| (a + 1..universe.len()).map(move |b| (a, b)))
+            .map(|(a, b)| universe.distance(a, b))
+            .sum::<isize>();
+        assert_eq!(universe.total_distance(), total);
+    }
 }
@@ -1,11 +1,12 @@
-use ahash::RandomState;
-use std::{collections::HashMap, io::BufRead};
+use std::{io::BufRead, iter::Sum, ops::AddAssign};
+
+use num_traits::{One, Zero};
 
 use itertools::{repeat_n, Itertools};
 use rayon::prelude::*;
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
-enum Status {
+pub(crate) enum Status {
     Damaged,
     Functional,
     Unknown,
@@ -22,134 +23,110 @@ impl From<char> for Status {
     }
 }
 
-pub fn star_one(input: impl BufRead) -> String {
-    let data = input
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-
-            let (statuses, continuous) = line.split_once(' ').unwrap();
-
-            let statuses = statuses.chars().map(|c| c.into()).collect::<Vec<Status>>();
-
-            let continuous = continuous
-                .split(',')
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<_>>();
-
-            (statuses, continuous)
-        })
+/// Repeats the record `factor` times, joining the statuses with unknowns.
+fn unfold(statuses: &str, continuous: &str, factor: usize) -> (Vec<Status>, Vec<usize>) {
+    let statuses = repeat_n(statuses, factor)
+        .join("?")
+        .chars()
+        .map(|c| c.into())
+        .collect::<Vec<Status>>();
+
+    let continuous = repeat_n(continuous, factor)
+        .join(",")
+        .split(',')
+        .map(|s| s.parse::<usize>().unwrap())
         .collect::<Vec<_>>();
 
-    data.into_par_iter()
-        .map(|(s, c)| {
-            let mut cache = HashMap::default();
-            process(&s, &c, 0, &mut cache)
-        })
-        .sum::<usize>()
-        .to_string()
+    (statuses, continuous)
 }
 
-fn process_functional<'a, 'b>(
-    statuses: &'a [Status],
-    continuous: &'b [usize],
-    current_count: usize,
-    cache: &mut Cache<'a, 'b>,
-) -> usize {
-    if current_count == 0 {
-        process(&statuses[1..], continuous, current_count, cache)
-    } else if continuous
-        .first()
-        .map(|&c| c == current_count)
-        .unwrap_or(false)
-    {
-        process(&statuses[1..], &continuous[1..], 0, cache)
-    } else {
-        0
-    }
-}
-type Cache<'a, 'b> = HashMap<(&'a [Status], &'b [usize], usize), usize, RandomState>;
-
-fn process_damaged<'a, 'b>(
-    statuses: &'a [Status],
-    continuous: &'b [usize],
-    current_count: usize,
-    cache: &mut Cache<'a, 'b>,
-) -> usize {
-    process(&statuses[1..], continuous, current_count + 1, cache)
-}
+/// Counts the ways the unknown statuses can be filled in to match the runs of damaged springs.
+/// The count type is generic so that large unfolds can be counted with a `BigUint`.
+///
+/// `ways[i][j]` is the number of arrangements of `statuses[i..]` that match `continuous[j..]`,
+/// starting at a position that is not in the middle of a run.
+pub(crate) fn count_arrangements<T>(statuses: &[Status], continuous: &[usize]) -> T
+where
+    T: Clone + Zero + One + for<'a> AddAssign<&'a T>,
+{
+    let n = statuses.len();
+
+    // The number of functional springs before each position, so we can check a run fits in O(1).
+    let functional_before = std::iter::once(0)
+        .chain(statuses.iter().scan(0, |count, status| {
+            *count += usize::from(status == &Status::Functional);
+            Some(*count)
+        }))
+        .collect::<Vec<_>>();
 
-fn process<'a, 'b>(
-    statuses: &'a [Status],
-    continuous: &'b [usize],
-    current_count: usize,
-    cache: &mut Cache<'a, 'b>,
-) -> usize {
-    if let Some(count) = cache.get(&(statuses, continuous, current_count)) {
-        return *count;
-    }
-    let res = match statuses.first() {
-        Some(Status::Functional) => process_functional(statuses, continuous, current_count, cache),
-
-        Some(Status::Damaged) => process_damaged(statuses, continuous, current_count, cache),
-        Some(Status::Unknown) => {
-            // We have an unknown status so try both functional and damaged
-            process_damaged(statuses, continuous, current_count, cache)
-                + process_functional(statuses, continuous, current_count, cache)
+    let mut ways = vec![vec![T::zero(); continuous.len() + 1]; n + 1];
+    ways[n][continuous.len()] = T::one();
+
+    for i in (0..n).rev() {
+        for j in 0..=continuous.len() {
+            let mut count = T::zero();
+
+            if statuses[i] != Status::Damaged {
+                count += &ways[i + 1][j];
+            }
+
+            if statuses[i] != Status::Functional {
+                if let Some(&run) = continuous.get(j) {
+                    let end = i + run;
+                    if end <= n
+                        && functional_before[end] == functional_before[i]
+                        && statuses.get(end) != Some(&Status::Damaged)
+                    {
+                        // Skip over the run and the functional spring that must follow it.
+                        count += &ways[(end + 1).min(n)][j + 1];
+                    }
+                }
+            }
+
+            ways[i][j] = count;
         }
-        // We are at the end
-        // We have no more continuous to check and there are no more damaged
-        None if continuous.is_empty() && current_count == 0 => 1,
-        // We have a single continuous to check and it matches the current count
-        None if continuous.len() == 1 && current_count == *continuous.first().unwrap() => 1,
-        // We have more continuous to check but no more statuses
-        None => 0,
-    };
-    cache.insert((statuses, continuous, current_count), res);
-    res
+    }
+
+    ways[0][0].clone()
 }
 
-pub fn star_two(input: impl BufRead) -> String {
+/// Sums the arrangements of every record after unfolding it `factor` times.
+pub fn sum_arrangements<T>(input: impl BufRead, factor: usize) -> T
+where
+    T: Clone + Zero + One + for<'a> AddAssign<&'a T> + Send + Sum,
+{
     let data = input
         .lines()
         .map(|line| {
             let line = line.unwrap();
-
             let (statuses, continuous) = line.split_once(' ').unwrap();
-
-            let statuses = repeat_n(statuses, 5).join("?");
-            let continuous = repeat_n(continuous, 5).join(",");
-
-            let statuses = statuses.chars().map(|c| c.into()).collect::<Vec<Status>>();
-
-            let continuous = continuous
-                .split(',')
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<_>>();
-
-            (statuses, continuous)
+            unfold(statuses, continuous, factor)
         })
         .collect::<Vec<_>>();
 
     data.into_par_iter()
-        .map(|(s, c)| {
-            let mut cache = HashMap::default();
-            process(&s, &c, 0, &mut cache)
-        })
-        .sum::<usize>()
-        .to_string()
+        .map(|(s, c)| count_arrangements::<T>(&s, &c))
+        .sum::<T>()
+}
+
+pub fn star_one(input: impl BufRead) -> String {
+    sum_arrangements::<u128>(input, 1).to_string()
+}
+
+pub fn star_two(input: impl BufRead) -> String {
+    sum_arrangements::<u128>(input, 5).to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_bigint::BigUint;
     use std::io::Cursor;
 
     #[test]
     fn test_process() {
         let data: Vec<Status> = "???.###".chars().map(|c| c.into()).collect::<Vec<_>>();
-        let mut cache = HashMap::default();
-        assert_eq!(process(&data, &[1, 1, 3], 0, &mut cache), 1);
+        assert_eq!(count_arrangements::<u128>(&data, &[1, 1, 3]), 1);
 
         let data: Vec<Status> = ".??..??...?##."
             .chars()
@@ -160,9 +137,65 @@ mod tests {
                 _ => unreachable!(),
             })
             .collect::<Vec<_>>();
-        let mut cache = HashMap::default();
 
-        assert_eq!(process(&data, &[1, 1, 3], 0, &mut cache), 4);
+        assert_eq!(count_arrangements::<u128>(&data, &[1, 1, 3]), 4);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let status = |s: &str| s.chars().map(|c| c.into()).collect::<Vec<Status>>();
+        let count_arrangements = count_arrangements::<u128>;
+        assert_eq!(count_arrangements(&status(""), &[]), 1);
+        assert_eq!(count_arrangements(&status(""), &[1]), 0);
+        assert_eq!(count_arrangements(&status("..."), &[]), 1);
+        assert_eq!(count_arrangements(&status(".#."), &[]), 0);
+        assert_eq!(count_arrangements(&status("???"), &[]), 1);
+        assert_eq!(count_arrangements(&status("???"), &[1]), 3);
+        assert_eq!(count_arrangements(&status("###"), &[3]), 1);
+        assert_eq!(count_arrangements(&status("####"), &[3]), 0);
+        assert_eq!(
+            count_arrangements(&status("?#?#?#?#?#?#?#?"), &[1, 3, 1, 6]),
+            1
+        );
+    }
+
+    #[test]
+    fn test_large_unfold() {
+        // 40 single runs in 119 unknowns is choosing 40 of 80 slots, which does not fit in a u64.
+        let (statuses, continuous) = unfold("??", "1", 40);
+        assert_eq!(statuses.len(), 119);
+        let binomial = (1..=40u128).fold(1, |acc, k| acc * (40 + k) / k);
+        assert_eq!(count_arrangements::<u128>(&statuses, &continuous), binomial);
+        assert!(binomial > u64::MAX as u128);
+
+        // This overflows a u128 so needs a big integer.
+        let (statuses, continuous) = unfold("?###????????", "3,2,1", 50);
+        let count = count_arrangements::<BigUint>(&statuses, &continuous);
+        assert!(count > BigUint::from(u128::MAX));
+
+        // Counting with a u128 and a big integer should agree while it fits.
+        let (statuses, continuous) = unfold("?###????????", "3,2,1", 20);
+        assert_eq!(
+            BigUint::from(count_arrangements::<u128>(&statuses, &continuous)),
+            count_arrangements::<BigUint>(&statuses, &continuous)
+        );
+    }
+
+    #[test]
+    fn test_unfold_factor() {
+        let input = b"???.### 1,1,3
+.??..??...?##. 1,1,3
+?#?#?#?#?#?#?#? 1,3,1,6
+????.#...#... 4,1,1
+????.######..#####. 1,6,5
+?###???????? 3,2,1";
+        assert_eq!(sum_arrangements::<u128>(Cursor::new(input), 1), 21);
+        assert_eq!(sum_arrangements::<u128>(Cursor::new(input), 5), 525152);
+        assert_eq!(sum_arrangements::<u128>(Cursor::new(&input[..13]), 50), 1);
+        assert_eq!(
+            sum_arrangements::<BigUint>(Cursor::new(input), 5),
+            BigUint::from(525152u32)
+        );
     }
 
     #[test]
@@ -1,26 +1,9 @@
 use std::{
     fmt::{Display, Formatter},
     io::BufRead,
-    ops::Deref,
 };
 
-#[derive(PartialEq, Debug, Clone)]
-enum Element {
-    Ash,
-    Rocks,
-}
-
-impl From<char> for Element {
-    fn from(value: char) -> Self {
-        match value {
-            '.' => Element::Ash,
-            '#' => Element::Rocks,
-            _ => unreachable!(),
-        }
-    }
-}
-
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 enum MirrorPosition {
     Row(usize),
     Column(usize),
@@ -35,174 +18,145 @@ impl Display for MirrorPosition {
     }
 }
 
-struct Map(Vec<Vec<Element>>);
-
-impl Display for Map {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for row in self.0.iter() {
-            for col in row.iter() {
-                write!(
-                    f,
-                    "{}",
-                    match col {
-                        Element::Ash => '.',
-                        Element::Rocks => '#',
-                    }
-                )?;
-            }
-            writeln!(f)?;
+impl MirrorPosition {
+    fn summary(&self) -> usize {
+        match self {
+            MirrorPosition::Row(row) => 100 * (row + 1),
+            MirrorPosition::Column(col) => col + 1,
         }
-        Ok(())
     }
 }
 
-struct MapGenerator {
-    map: Map,
-    position: (usize, usize),
+#[derive(PartialEq, Debug)]
+struct Reflection {
+    position: MirrorPosition,
+    /// The (row, column) of each cell that needs flipping for the reflection to be perfect.
+    smudges: Vec<(usize, usize)>,
 }
 
-impl Iterator for MapGenerator {
-    type Item = Map;
+/// A row or column as a bitset split into 64-bit words, where bit `i % 64` of word `i / 64` is
+/// set if cell `i` is rocks.
+type Line = Vec<u64>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut map = self.map.clone();
+/// The number of cells that differ between two lines of the same length.
+fn differences(a: &Line, b: &Line) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
 
-        let (x, y) = self.position;
+/// A pattern stored as bitsets, where bit `i` of a row is set if column `i` is rocks (and
+/// likewise for the columns).
+struct Pattern {
+    rows: Vec<Line>,
+    columns: Vec<Line>,
+}
 
-        if y >= map.len() {
-            return None;
-        }
+impl Pattern {
+    fn parse(input: &str) -> Self {
+        let grid = input
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
 
-        map[y][x] = match map[y][x] {
-            Element::Ash => Element::Rocks,
-            Element::Rocks => Element::Ash,
+        let to_mask = |bits: &mut dyn Iterator<Item = bool>| {
+            bits.enumerate()
+                .fold(Vec::new(), |mut mask: Line, (i, rock)| {
+                    if i % 64 == 0 {
+                        mask.push(0);
+                    }
+                    mask[i / 64] |= u64::from(rock) << (i % 64);
+                    mask
+                })
         };
 
-        self.position = if x + 1 < map[y].len() {
-            (x + 1, y)
-        } else {
-            (0, y + 1)
-        };
+        let rows = grid
+            .iter()
+            .map(|row| to_mask(&mut row.iter().copied()))
+            .collect();
+        let columns = (0..grid[0].len())
+            .map(|x| to_mask(&mut grid.iter().map(|row| row[x])))
+            .collect();
 
-        Some(Map(map))
+        Pattern { rows, columns }
     }
-}
 
-impl Deref for Map {
-    type Target = Vec<Vec<Element>>;
+    /// Finds every mirror between line `i` and `i + 1` where exactly `smudges` cells differ,
+    /// returning the line and the differing cells as (line, offset along the line).
+    fn mirror_lines(lines: &[Line], smudges: u32) -> Vec<(usize, Vec<(usize, usize)>)> {
+        (0..lines.len().saturating_sub(1))
+            .filter_map(|i| {
+                let pairs = (0..=i).rev().zip(i + 1..lines.len());
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-fn find_mirror(group: &Map, existing: Option<&MirrorPosition>) -> Option<MirrorPosition> {
-    // Maybe only need to do half
-    for row in 0..group.len() {
-        let length = (row + 1).min(group.len() - row - 1);
-        let offset = row + 1 - length;
-
-        if length > 0
-            && (0..length)
-                // FIXME needs to be adjust to row
-                .all(|i| {
-                    &group[offset + i]
-                        == group.get(offset + length * 2 - i - 1).unwrap_or_else(|| {
-                            panic!(
-                                "Trying to compare when offset is \n{group}\n\n{offset}, {length}, {row}, {i}"
-                            )
-                        })
-                })
-        {
-            if let Some(existing) = existing {
-                if existing == &MirrorPosition::Row(row) {
-                    continue;
+                let mut differences = 0;
+                for (a, b) in pairs.clone() {
+                    differences += self::differences(&lines[a], &lines[b]);
+                    if differences > smudges {
+                        return None;
+                    }
                 }
-            }
-            return Some(MirrorPosition::Row(row));
-        }
+                if differences != smudges {
+                    return None;
+                }
+
+                let cells = pairs
+                    .flat_map(|(a, b)| {
+                        lines[a].iter().zip(&lines[b]).enumerate().flat_map(
+                            move |(word, (x, y))| {
+                                let diff = x ^ y;
+                                (0..64)
+                                    .filter(move |bit| diff & (1 << bit) != 0)
+                                    .map(move |bit| (a, word * 64 + bit))
+                            },
+                        )
+                    })
+                    .collect();
+                Some((i, cells))
+            })
+            .collect()
     }
 
-    let col_length = group[0].len();
+    /// Returns every reflection that would be perfect after fixing exactly `smudges` cells.
+    fn reflections(&self, smudges: u32) -> Vec<Reflection> {
+        let rows = Pattern::mirror_lines(&self.rows, smudges)
+            .into_iter()
+            .map(|(row, cells)| Reflection {
+                position: MirrorPosition::Row(row),
+                smudges: cells,
+            });
 
-    for col in 0..col_length {
-        let length = (col + 1).min(col_length - col - 1);
-        let offset = col + 1 - length;
-        if length > 0
-            && (0..group.len()).all(|y| {
-                (0..length).all(|i| group[y][offset + i] == group[y][offset + length * 2 - i - 1])
-            })
-        {
-            if let Some(existing) = existing {
-                if existing == &MirrorPosition::Column(col) {
-                    continue;
-                }
-            }
-            return Some(MirrorPosition::Column(col));
-        }
+        let columns = Pattern::mirror_lines(&self.columns, smudges)
+            .into_iter()
+            .map(|(col, cells)| Reflection {
+                position: MirrorPosition::Column(col),
+                smudges: cells.into_iter().map(|(x, y)| (y, x)).collect(),
+            });
+
+        rows.chain(columns).collect()
     }
-    None
 }
 
-pub fn star_one(mut input: impl BufRead) -> String {
+fn summarise(mut input: impl BufRead, smudges: u32) -> usize {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
     str.split("\n\n")
         .map(|group| {
-            group
-                .lines()
-                .map(|line| line.chars().map(Element::from).collect::<Vec<_>>())
-                .collect::<Vec<Vec<_>>>()
-        })
-        .map(|group| {
-            // find the mirror
-            let mirror = find_mirror(&Map(group), None).unwrap();
-
-            match mirror {
-                MirrorPosition::Row(row) => 100 * (row + 1),
-                MirrorPosition::Column(col) => col + 1,
-            }
+            let pattern = Pattern::parse(group);
+            let reflections = pattern.reflections(smudges);
+            reflections
+                .first()
+                .unwrap_or_else(|| panic!("No reflection found in\n{group}"))
+                .position
+                .summary()
         })
         .sum::<usize>()
-        .to_string()
 }
 
-pub fn star_two(mut input: impl BufRead) -> String {
-    let mut str = String::new();
-    input.read_to_string(&mut str).expect("cannot read string");
+pub fn star_one(input: impl BufRead) -> String {
+    summarise(input, 0).to_string()
+}
 
-    let maps = str
-        .split("\n\n")
-        .map(|group| {
-            group
-                .lines()
-                .map(|line| line.chars().map(Element::from).collect::<Vec<_>>())
-                .collect::<Vec<Vec<_>>>()
-        })
-        .collect::<Vec<_>>();
-    maps.into_iter()
-        .map(|group| {
-            let map = Map(group);
-            let mirror = find_mirror(&map, None).unwrap();
-            // find the mirror
-            let generator = MapGenerator {
-                map,
-                position: (0, 0),
-            };
-            let mirror = generator
-                .into_iter()
-                .filter_map(|map| find_mirror(&map, Some(&mirror)))
-                .find(|position| *position != mirror)
-                .unwrap();
-
-            match mirror {
-                MirrorPosition::Row(row) => 100 * (row + 1),
-                MirrorPosition::Column(col) => col + 1,
-            }
-        })
-        .sum::<usize>()
-        .to_string()
+pub fn star_two(input: impl BufRead) -> String {
+    summarise(input, 1).to_string()
 }
 
 #[cfg(test)]
@@ -210,6 +164,101 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    #[test]
+    fn test_reflections() {
+        let pattern = Pattern::parse(
+            "#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.",
+        );
+        assert_eq!(
+            pattern.reflections(0),
+            vec![Reflection {
+                position: MirrorPosition::Column(4),
+                smudges: vec![]
+            }]
+        );
+        assert_eq!(
+            pattern.reflections(1),
+            vec![Reflection {
+                position: MirrorPosition::Row(2),
+                smudges: vec![(0, 0)]
+            }]
+        );
+
+        let pattern = Pattern::parse(
+            "#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#",
+        );
+        assert_eq!(
+            pattern.reflections(1),
+            vec![Reflection {
+                position: MirrorPosition::Row(0),
+                smudges: vec![(0, 4)]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_multiple_smudges() {
+        let pattern = Pattern::parse(
+            "##..
+.#..
+#...",
+        );
+        // Columns 0 and 1 differ in two cells, rows 0 and 1 differ in one.
+        let reflections = pattern.reflections(2);
+        assert!(reflections.contains(&Reflection {
+            position: MirrorPosition::Column(0),
+            smudges: vec![(1, 0), (2, 0)]
+        }));
+        for reflection in pattern.reflections(2) {
+            assert_eq!(reflection.smudges.len(), 2);
+        }
+        assert_eq!(MirrorPosition::Column(0).to_string(), "column 0");
+    }
+
+    #[test]
+    fn test_large_pattern() {
+        // Longer than a single word, with the smudge past the first 64 cells.
+        let base = (0..100).map(|x| x * x % 7 < 3).collect::<Vec<_>>();
+        let mut smudged = base.clone();
+        smudged[70] = !smudged[70];
+        let inverse = base.iter().map(|rock| !rock).collect::<Vec<_>>();
+        let grid = [base, smudged, inverse];
+
+        let render = |lines: Vec<String>| lines.join("\n");
+        let cell = |rock: bool| if rock { '#' } else { '.' };
+        let wide = Pattern::parse(&render(
+            grid.iter()
+                .map(|row| row.iter().map(|&rock| cell(rock)).collect())
+                .collect(),
+        ));
+        assert!(wide.reflections(1).contains(&Reflection {
+            position: MirrorPosition::Row(0),
+            smudges: vec![(0, 70)]
+        }));
+
+        let tall = Pattern::parse(&render(
+            (0..100)
+                .map(|y| grid.iter().map(|column| cell(column[y])).collect())
+                .collect(),
+        ));
+        assert!(tall.reflections(1).contains(&Reflection {
+            position: MirrorPosition::Column(0),
+            smudges: vec![(70, 0)]
+        }));
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
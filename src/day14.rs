@@ -1,217 +1,351 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    hash::{Hash, Hasher},
     io::BufRead,
-    ops::Add,
     str::FromStr,
 };
 
-#[derive(PartialEq, Hash, Debug, Clone, Copy, Eq)]
-enum Rock {
-    Square,
-    Round,
-}
+use crate::cycle;
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
-enum Direction {
+pub enum Direction {
     North,
     West,
     South,
     East,
 }
 
-impl Add<(isize, isize)> for Direction {
-    type Output = (isize, isize);
+impl TryFrom<char> for Direction {
+    type Error = String;
 
-    fn add(self, rhs: (isize, isize)) -> Self::Output {
-        match self {
-            Direction::North => (rhs.0 - 1, rhs.1),
-            Direction::West => (rhs.0, rhs.1 - 1),
-            Direction::South => (rhs.0 + 1, rhs.1),
-            Direction::East => (rhs.0, rhs.1 + 1),
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'N' => Ok(Direction::North),
+            'W' => Ok(Direction::West),
+            'S' => Ok(Direction::South),
+            'E' => Ok(Direction::East),
+            x => Err(format!("Invalid direction {x}")),
         }
     }
 }
 
-struct Map(Vec<Vec<Option<Rock>>>);
+/// The `start..end` runs of cells between square rocks along each line.
+fn segments(squares: &[u128], length: usize) -> Vec<Vec<(usize, usize)>> {
+    squares
+        .iter()
+        .map(|&line| {
+            let mut segments = Vec::new();
+            let mut start = 0;
+            for i in 0..=length {
+                if i == length || line & (1 << i) != 0 {
+                    if start < i {
+                        segments.push((start, i));
+                    }
+                    start = i + 1;
+                }
+            }
+            segments
+        })
+        .collect()
+}
 
-impl Map {
-    fn weight(&self) -> usize {
-        let total_rows = self.0.len();
-        self.0
-            .iter()
-            .enumerate()
-            .flat_map(|(i, row)| {
-                row.iter()
-                    .filter(|rock| **rock == Some(Rock::Round))
-                    .map(move |_| total_rows - i)
-            })
-            .sum::<usize>()
-    }
+/// The lowest `n` bits set, for any `n` up to the full 128.
+fn low_bits(n: usize) -> u128 {
+    u128::MAX.checked_shr((128 - n) as u32).unwrap_or(0)
+}
 
-    fn tilt(&mut self, direction: Direction) {
-        // Roll the rocks
-        // find rollable rock
-        while let Some(pos) = self.get_next_rollable(direction) {
-            let mut next_pos: (isize, isize) = (pos.0 as isize, pos.1 as isize);
-
-            // While the next position is empty `Some(None)` i.e. there is a place but it is not
-            // filled with anything
-            while self
-                .0
-                .get((direction + next_pos).0 as usize)
-                .and_then(|row| row.get((direction + next_pos).1 as usize))
-                == Some(&None)
-            {
-                next_pos = direction + next_pos;
-            }
+/// Moves every round rock in `line` to the low (or high) end of its segment.
+fn tilt_line(line: u128, segments: &[(usize, usize)], towards_low: bool) -> u128 {
+    segments.iter().fold(0, |tilted, &(start, end)| {
+        let mask = low_bits(end - start) << start;
+        let count = (line & mask).count_ones() as usize;
+        let start = if towards_low { start } else { end - count };
+        tilted | low_bits(count).checked_shl(start as u32).unwrap_or(0)
+    })
+}
 
-            let r = self.0[pos.0][pos.1].take();
-            self.0[next_pos.0 as usize][next_pos.1 as usize] = r;
+/// Transposes a bitboard so that bit `j` of line `i` becomes bit `i` of line `j`.
+fn transpose(lines: &[u128], length: usize) -> Vec<u128> {
+    let mut transposed = vec![0; length];
+    for (i, &line) in lines.iter().enumerate() {
+        let mut bits = line;
+        while bits != 0 {
+            let j = bits.trailing_zeros() as usize;
+            transposed[j] |= 1 << i;
+            bits &= bits - 1;
         }
     }
+    transposed
+}
+
+/// The platform as bitboards, where bit `x` of row `y` is set for a rock at (x, y). Only the
+/// round rocks move so only they are part of the state.
+#[derive(Debug, Clone)]
+pub struct Platform {
+    width: usize,
+    height: usize,
+    rows: Vec<u128>,
+    row_segments: Vec<Vec<(usize, usize)>>,
+    column_segments: Vec<Vec<(usize, usize)>>,
+}
+
+impl Platform {
+    pub fn load(&self) -> usize {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(y, row)| row.count_ones() as usize * (self.height - y))
+            .sum()
+    }
 
-    fn get_next_rollable(&self, direction: Direction) -> Option<(usize, usize)> {
+    pub fn tilt(&mut self, direction: Direction) {
         match direction {
-            Direction::North => {
-                for j in 0..self.0[0].len() {
-                    if let Some(x) = self
-                        .0
-                        .iter()
-                        .enumerate()
-                        .skip(1)
-                        .filter(|(_i, row)| row[j] == Some(Rock::Round))
-                        .filter(|(i, _)| self.0[i - 1][j].is_none())
-                        .map(|(i, _row)| (i, j))
-                        .next()
-                    {
-                        return Some(x);
-                    }
-                }
-            }
-            Direction::West => {
-                for (i, row) in self.0.iter().enumerate() {
-                    if let Some(x) = row
-                        .iter()
-                        .enumerate()
-                        .skip(1)
-                        .filter(|(_i, c)| c == &&Some(Rock::Round))
-                        .filter(|(j, _)| self.0[i][j - 1].is_none())
-                        .map(|(j, _)| (i, j))
-                        .next()
-                    {
-                        return Some(x);
-                    }
-                }
-            }
-            Direction::South => {
-                for j in (0..self.0[0].len()).rev() {
-                    if let Some(pos) = self
-                        .0
-                        .iter()
-                        .enumerate()
-                        .rev()
-                        .skip(1)
-                        .filter(|(_i, row)| row[j] == Some(Rock::Round))
-                        .filter(|(i, _)| self.0[i + 1][j].is_none())
-                        .map(|(i, _)| (i, j))
-                        .next()
-                    {
-                        return Some(pos);
-                    }
+            Direction::West | Direction::East => {
+                for (row, segments) in self.rows.iter_mut().zip(&self.row_segments) {
+                    *row = tilt_line(*row, segments, direction == Direction::West);
                 }
             }
-            Direction::East => {
-                for (i, row) in self.0.iter().enumerate().rev() {
-                    if let Some(pos) = row
-                        .iter()
-                        .enumerate()
-                        .rev()
-                        .skip(1)
-                        .filter(|(_j, c)| c == &&Some(Rock::Round))
-                        .filter(|(j, _)| self.0[i][j + 1].is_none())
-                        .map(|(j, _)| (i, j))
-                        .next()
-                    {
-                        return Some(pos);
-                    }
-                }
+            Direction::North | Direction::South => {
+                let columns = transpose(&self.rows, self.width)
+                    .iter()
+                    .zip(&self.column_segments)
+                    .map(|(&column, segments)| {
+                        tilt_line(column, segments, direction == Direction::North)
+                    })
+                    .collect::<Vec<_>>();
+                self.rows = transpose(&columns, self.height);
             }
         }
-        None
+    }
+
+    /// Applies each tilt in `program` in turn, e.g. "NWSE" for a spin cycle.
+    pub fn run(&mut self, program: &[Direction]) {
+        for &direction in program {
+            self.tilt(direction);
+        }
+    }
+
+    /// Returns the load after `steps` single tilts of `program` repeated forever. Repeats of
+    /// the whole program are skipped once the rocks start cycling. An empty program leaves the
+    /// rocks where they are.
+    pub fn load_after(&self, program: &[Direction], steps: usize) -> usize {
+        if program.is_empty() {
+            return self.load();
+        }
+        let (mut platform, _) = cycle::hashed(
+            self.clone(),
+            |platform| {
+                let mut platform = platform.clone();
+                platform.run(program);
+                platform
+            },
+            steps / program.len(),
+        );
+
+        platform.run(&program[..steps % program.len()]);
+        platform.load()
+    }
+}
+
+impl PartialEq for Platform {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows
+    }
+}
+
+impl Eq for Platform {}
+
+impl Hash for Platform {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rows.hash(state);
     }
 }
 
-impl FromStr for Map {
+impl FromStr for Platform {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(
-            s.lines()
-                .map(|line| {
-                    line.chars()
-                        .map(|c| match c {
-                            '.' => None,
-                            '#' => Some(Rock::Square),
-                            'O' => Some(Rock::Round),
-                            _ => panic!("invalid input"),
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>(),
-        ))
+        let lines = s.lines().collect::<Vec<_>>();
+        let height = lines.len();
+        let width = lines.first().map(|line| line.len()).unwrap_or(0);
+        if width > 128 || height > 128 {
+            return Err(format!("Platform {width}x{height} is larger than 128x128"));
+        }
+
+        let mut rows = vec![0u128; height];
+        let mut squares = vec![0u128; height];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    '#' => squares[y] |= 1 << x,
+                    'O' => rows[y] |= 1 << x,
+                    x => return Err(format!("Invalid input {x}")),
+                }
+            }
+        }
+
+        Ok(Platform {
+            width,
+            height,
+            rows,
+            row_segments: segments(&squares, width),
+            column_segments: segments(&transpose(&squares, width), height),
+        })
     }
 }
 
+pub fn parse_program(program: &str) -> Result<Vec<Direction>, String> {
+    program.chars().map(Direction::try_from).collect()
+}
+
 pub fn star_one(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
-    let mut map: Map = str.parse().unwrap();
+    let mut platform: Platform = str.parse().unwrap();
 
-    map.tilt(Direction::North);
+    platform.tilt(Direction::North);
 
-    map.weight().to_string()
+    platform.load().to_string()
 }
 
 pub fn star_two(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).expect("cannot read string");
 
-    let mut map: Map = str.parse().unwrap();
+    let platform: Platform = str.parse().unwrap();
+    let program = parse_program("NWSE").unwrap();
+
+    platform
+        .load_after(&program, 1_000_000_000 * program.len())
+        .to_string()
+}
 
-    let number_of_cycles = 1_000_000_000;
-    let mut cache = HashMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
 
-    let mut cycle = 0;
+    const INPUT: &str = "O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
 
-    while cycle < number_of_cycles {
-        cycle += match cache.entry(map.0.clone()) {
-            Entry::Occupied(last_i) => {
-                let cycle_length = cycle - last_i.get();
-                let cycles_left = number_of_cycles - cycle;
-                (cycles_left / cycle_length) * cycle_length
-            }
-            Entry::Vacant(e) => {
-                e.insert(cycle);
-                0
-            }
+    /// Tilts one rock at a time, as a slow reference to check the bitboards against.
+    fn brute_force_tilt(grid: &mut [Vec<char>], direction: Direction) {
+        let (dy, dx) = match direction {
+            Direction::North => (-1, 0),
+            Direction::West => (0, -1),
+            Direction::South => (1, 0),
+            Direction::East => (0, 1),
         };
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for y in 0..grid.len() {
+                for x in 0..grid[0].len() {
+                    let (ny, nx) = (y as isize + dy, x as isize + dx);
+                    if grid[y][x] == 'O'
+                        && ny >= 0
+                        && nx >= 0
+                        && (ny as usize) < grid.len()
+                        && (nx as usize) < grid[0].len()
+                        && grid[ny as usize][nx as usize] == '.'
+                    {
+                        grid[ny as usize][nx as usize] = 'O';
+                        grid[y][x] = '.';
+                        moved = true;
+                    }
+                }
+            }
+        }
+    }
 
-        map.tilt(Direction::North);
-        map.tilt(Direction::West);
-        map.tilt(Direction::South);
-        map.tilt(Direction::East);
+    #[test]
+    fn test_tilt_matches_brute_force() {
+        let mut platform: Platform = INPUT.parse().unwrap();
+        let mut grid = INPUT
+            .lines()
+            .map(|line| line.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
 
-        cycle += 1;
+        for direction in parse_program("NWSEENSWWNSE").unwrap() {
+            platform.tilt(direction);
+            brute_force_tilt(&mut grid, direction);
+            let expected = grid
+                .iter()
+                .map(|row| row.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .parse::<Platform>()
+                .unwrap();
+            assert_eq!(platform.rows, expected.rows);
+        }
     }
 
-    map.weight().to_string()
-}
+    #[test]
+    fn test_full_width() {
+        // Rows and columns as long as the bitboards, including segments with no square rocks.
+        let input = (0..128)
+            .map(|y| {
+                (0..128)
+                    .map(|x| match (x, y) {
+                        (_, 0) | (5, _) => 'O',
+                        (x, y) if x == y && y > 64 => '#',
+                        (x, y) if (x * 7 + y * 3) % 11 == 0 => 'O',
+                        _ => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut platform: Platform = input.parse().unwrap();
+        let mut grid = input
+            .lines()
+            .map(|line| line.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+        for direction in parse_program("WNESW").unwrap() {
+            platform.tilt(direction);
+            brute_force_tilt(&mut grid, direction);
+            let expected = grid
+                .iter()
+                .map(|row| row.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .parse::<Platform>()
+                .unwrap();
+            assert_eq!(platform.rows, expected.rows);
+        }
+
+        assert!(".".repeat(129).parse::<Platform>().is_err());
+    }
+
+    #[test]
+    fn test_load_after() {
+        let platform: Platform = INPUT.parse().unwrap();
+        let program = parse_program("NWSE").unwrap();
+        assert_eq!(platform.load_after(&program, 0), 104);
+        assert_eq!(platform.load_after(&program, 1), 136);
+        assert_eq!(platform.load_after(&program, 4 * 1_000_000_000), 64);
+
+        // Check skipping ahead against running every step of an uneven program.
+        let program = parse_program("NNES").unwrap();
+        let mut stepped = platform.clone();
+        for steps in 0..200 {
+            assert_eq!(platform.load_after(&program, steps), stepped.load());
+            stepped.tilt(program[steps % program.len()]);
+        }
+
+        assert_eq!(platform.load_after(&parse_program("").unwrap(), 10), 104);
+        assert!(parse_program("NX").is_err());
+    }
 
     #[test]
     fn test_star_one() {
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, io::BufRead};
+use std::{
+    fmt::{Display, Formatter},
+    io::BufRead,
+    str::FromStr,
+};
 
 fn hash(str: &str) -> usize {
     let mut value = 0;
@@ -16,56 +20,161 @@ pub fn star_one(mut input: impl BufRead) -> String {
     str.split(',').map(hash).sum::<usize>().to_string()
 }
 
-#[derive(Debug)]
-enum Operation {
-    Equal(isize),
-    Dash,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Insert { label: String, focal_length: usize },
+    Remove { label: String },
 }
 
-pub fn star_two(mut input: impl BufRead) -> String {
-    let mut str = String::new();
-    input.read_to_string(&mut str).expect("cannot read string");
+impl Step {
+    /// The box holding the step's lens, which is the only one the step changes.
+    pub fn box_index(&self) -> usize {
+        match self {
+            Step::Insert { label, .. } | Step::Remove { label } => hash(label),
+        }
+    }
+}
 
-    let boxes = str
-        .split(',')
-        .map(|s| {
-            if let Some((label, integer)) = s.split_once('=') {
-                (Operation::Equal(integer.parse::<isize>().unwrap()), label)
-            } else {
-                (Operation::Dash, s.trim_matches('-'))
-            }
-        })
-        .fold(HashMap::new(), |mut state, (op, label)| {
-            match op {
-                Operation::Equal(value) => {
-                    let lens_box: &mut Vec<(&str, isize)> = state.entry(hash(label)).or_default();
-                    if let Some(v) = lens_box.iter_mut().find(|v| v.0 == label) {
-                        v.1 = value;
-                    } else {
-                        lens_box.push((label, value));
-                    }
-                }
-                Operation::Dash => {
-                    if let Some(values) = state.get_mut(&hash(label)) {
-                        values.retain_mut(|v| v.0 != label);
-                    }
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((label, focal_length)) = s.split_once('=') {
+            Ok(Step::Insert {
+                label: label.to_string(),
+                focal_length: focal_length
+                    .parse()
+                    .map_err(|_| format!("Invalid focal length in {s}"))?,
+            })
+        } else if let Some(label) = s.strip_suffix('-') {
+            Ok(Step::Remove {
+                label: label.to_string(),
+            })
+        } else {
+            Err(format!("Invalid step {s}"))
+        }
+    }
+}
+
+/// Reads the comma separated steps one at a time, so long logs never need to be held in memory.
+pub fn steps(input: impl BufRead) -> impl Iterator<Item = Result<Step, String>> {
+    input.split(b',').map(|step| {
+        let step = step.map_err(|e| e.to_string())?;
+        String::from_utf8_lossy(&step).trim().parse()
+    })
+}
+
+/// The 256 boxes of lenses, each holding its lenses in order as (label, focal length).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LensLibrary {
+    boxes: Vec<Vec<(String, usize)>>,
+}
+
+impl Default for LensLibrary {
+    fn default() -> Self {
+        LensLibrary {
+            boxes: vec![Vec::new(); 256],
+        }
+    }
+}
+
+impl LensLibrary {
+    pub fn apply(&mut self, step: &Step) {
+        match step {
+            Step::Insert {
+                label,
+                focal_length,
+            } => {
+                let lens_box = &mut self.boxes[hash(label)];
+                if let Some(lens) = lens_box.iter_mut().find(|lens| &lens.0 == label) {
+                    lens.1 = *focal_length;
+                } else {
+                    lens_box.push((label.clone(), *focal_length));
                 }
             }
-            state
-        });
-    // println!("{:?}", boxes);
-    boxes
-        .iter()
-        .map(|(key, values)| {
-            values
-                .iter()
-                .enumerate()
-                .map(|(i, v)| (i + 1) * v.1 as usize)
-                .sum::<usize>()
-                * (key + 1)
-        })
-        .sum::<usize>()
-        .to_string()
+            Step::Remove { label } => {
+                self.boxes[hash(label)].retain(|lens| &lens.0 != label);
+            }
+        }
+    }
+
+    pub fn box_contents(&self, index: usize) -> &[(String, usize)] {
+        &self.boxes[index]
+    }
+
+    pub fn box_power(&self, index: usize) -> usize {
+        self.boxes[index]
+            .iter()
+            .enumerate()
+            .map(|(i, (_, focal_length))| (index + 1) * (i + 1) * focal_length)
+            .sum()
+    }
+
+    pub fn focusing_power(&self) -> usize {
+        (0..self.boxes.len()).map(|i| self.box_power(i)).sum()
+    }
+}
+
+impl Display for LensLibrary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, lens_box) in self.boxes.iter().enumerate() {
+            if lens_box.is_empty() {
+                continue;
+            }
+            write!(f, "Box {i}:")?;
+            for (label, focal_length) in lens_box {
+                write!(f, " [{label} {focal_length}]")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays two instruction logs side by side and returns the number of steps applied when their
+/// libraries first differ, or `None` if they end in the same state.
+pub fn first_divergence(
+    a: impl IntoIterator<Item = Step>,
+    b: impl IntoIterator<Item = Step>,
+) -> Option<usize> {
+    let mut a = a.into_iter();
+    let mut b = b.into_iter();
+    let mut library_a = LensLibrary::default();
+    let mut library_b = LensLibrary::default();
+
+    let mut applied = 0;
+    loop {
+        let (step_a, step_b) = (a.next(), b.next());
+        if step_a.is_none() && step_b.is_none() {
+            return None;
+        }
+        applied += 1;
+
+        let mut touched = Vec::with_capacity(2);
+        if let Some(step) = step_a {
+            library_a.apply(&step);
+            touched.push(step.box_index());
+        }
+        if let Some(step) = step_b {
+            library_b.apply(&step);
+            touched.push(step.box_index());
+        }
+        // The libraries matched before these steps, so only the boxes they touched can differ.
+        if touched
+            .into_iter()
+            .any(|index| library_a.box_contents(index) != library_b.box_contents(index))
+        {
+            return Some(applied);
+        }
+    }
+}
+
+pub fn star_two(input: impl BufRead) -> String {
+    let mut library = LensLibrary::default();
+    for step in steps(input) {
+        library.apply(&step.unwrap());
+    }
+    library.focusing_power().to_string()
 }
 
 #[cfg(test)]
@@ -78,6 +187,63 @@ mod tests {
         assert_eq!(hash("HASH"), 52);
     }
 
+    const INPUT: &[u8] = b"rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7\n";
+
+    #[test]
+    fn test_lens_library() {
+        let steps = steps(Cursor::new(INPUT))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(steps.len(), 11);
+        assert_eq!(
+            steps[1],
+            Step::Remove {
+                label: "cm".to_string()
+            }
+        );
+
+        let mut library = LensLibrary::default();
+        for step in &steps[..3] {
+            library.apply(step);
+        }
+        assert_eq!(library.to_string(), "Box 0: [rn 1]\nBox 1: [qp 3]\n");
+
+        for step in &steps[3..] {
+            library.apply(step);
+        }
+        assert_eq!(
+            library.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+        assert_eq!(
+            library.box_contents(0),
+            &[("rn".to_string(), 1), ("cm".to_string(), 2)]
+        );
+        assert_eq!(library.box_power(0), 5);
+        assert_eq!(library.box_power(3), 140);
+        assert_eq!(library.focusing_power(), 145);
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let steps = steps(Cursor::new(INPUT))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(first_divergence(steps.clone(), steps.clone()), None);
+
+        let mut changed = steps.clone();
+        changed[4] = Step::Remove {
+            label: "rn".to_string(),
+        };
+        assert_eq!(first_divergence(steps.clone(), changed), Some(5));
+
+        assert_eq!(
+            first_divergence(steps.clone(), steps[..10].to_vec()),
+            Some(11)
+        );
+        assert!("ab".parse::<Step>().is_err());
+    }
+
     #[test]
     fn test_star_one() {
         assert_eq!(
@@ -1,12 +1,15 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     io::BufRead,
     ops::{Add, Deref},
     str::FromStr,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
+use petgraph::{algo::tarjan_scc, graphmap::GraphMap, Directed};
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
     Up,
     Down,
     Left,
@@ -26,7 +29,33 @@ impl Add<(isize, isize)> for Direction {
     }
 }
 
-enum Position {
+impl Direction {
+    fn arrow(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+}
+
+impl TryFrom<char> for Direction {
+    type Error = String;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'U' | '^' => Ok(Direction::Up),
+            'D' | 'v' => Ok(Direction::Down),
+            'L' | '<' => Ok(Direction::Left),
+            'R' | '>' => Ok(Direction::Right),
+            x => Err(format!("Invalid direction {x}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
     Empty,
     LeftMirror,
     RightMirror,
@@ -34,7 +63,7 @@ enum Position {
     HoritzontalSplitter,
 }
 
-struct Grid(Vec<Vec<Position>>);
+pub struct Grid(Vec<Vec<Position>>);
 
 impl FromStr for Grid {
     type Err = String;
@@ -53,6 +82,12 @@ impl FromStr for Grid {
                     x => return Err(format!("Invalid character {x}")),
                 });
             }
+            if grid
+                .first()
+                .is_some_and(|first: &Vec<Position>| first.len() != row.len())
+            {
+                return Err(format!("Row {} has a different width", grid.len()));
+            }
             grid.push(row);
         }
         Ok(Grid(grid))
@@ -67,36 +102,22 @@ impl Grid {
         self.0.get(y as usize).and_then(|row| row.get(x as usize))
     }
 
+    fn width(&self) -> usize {
+        self.0.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Every beam that can enter the grid from one of its edges.
     fn starting_positions(&self) -> Vec<((isize, isize), Direction)> {
-        // All positions on the top row
-        let top_positions = self
-            .0
-            .first()
-            .map(|row| {
-                row.iter()
-                    .enumerate()
-                    .map(|(i, _c)| ((i as isize, 0), Direction::Down))
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
-            .into_iter();
-
-        let bottom_positions = self
-            .0
-            .last()
-            .map(|row| {
-                row.iter()
-                    .enumerate()
-                    .map(|(i, _c)| ((i as isize, self.0.len() as isize - 1), Direction::Up))
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
-            .into_iter();
-
-        let left_positions = (0..self.0.len()).map(|i| ((0, i as isize), Direction::Right));
-
-        let right_positions =
-            (0..self.0.len()).map(|i| ((self.0.len() as isize - 1, i as isize), Direction::Left));
+        let (width, height) = (self.width() as isize, self.height() as isize);
+
+        let top_positions = (0..width).map(|x| ((x, 0), Direction::Down));
+        let bottom_positions = (0..width).map(|x| ((x, height - 1), Direction::Up));
+        let left_positions = (0..height).map(|y| ((0, y), Direction::Right));
+        let right_positions = (0..height).map(|y| ((width - 1, y), Direction::Left));
 
         top_positions
             .chain(bottom_positions)
@@ -114,86 +135,307 @@ impl Deref for Grid {
     }
 }
 
-pub fn star_one(mut input: impl BufRead) -> String {
-    let mut str = String::new();
-    input.read_to_string(&mut str).unwrap();
+/// A beam entering the tile at the position, travelling in the direction.
+pub type Beam = ((isize, isize), Direction);
 
-    let map = str.parse::<Grid>().unwrap();
+/// A set of tiles stored as a bitset so that unions are cheap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TileSet {
+    width: usize,
+    bits: Vec<u64>,
+}
 
-    let mut seen: HashSet<((isize, isize), Direction)> = HashSet::new();
-    let mut stack = Vec::new();
-    stack.push(((0, 0), Direction::Right));
+impl TileSet {
+    fn new(width: usize, height: usize) -> Self {
+        TileSet {
+            width,
+            bits: vec![0; (width * height).div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, (x, y): (isize, isize)) {
+        let i = y as usize * self.width + x as usize;
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+
+    fn contains(&self, (x, y): (isize, isize)) -> bool {
+        let i = y as usize * self.width + x as usize;
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
 
-    while let Some((pos, dir)) = stack.pop() {
-        if seen.contains(&(pos, dir)) {
-            continue;
+    fn union_with(&mut self, other: &TileSet) {
+        for (a, b) in self.bits.iter_mut().zip(&other.bits) {
+            *a |= b;
         }
-        seen.insert((pos, dir));
-        match map.get(pos.0, pos.1) {
-            Some(Position::Empty) => {
-                stack.push((dir + pos, dir));
-            }
-            Some(Position::LeftMirror) => {
-                let new_dir = match dir {
-                    Direction::Up => Direction::Right,
-                    Direction::Down => Direction::Left,
-                    Direction::Left => Direction::Down,
-                    Direction::Right => Direction::Up,
-                };
-                stack.push((new_dir + pos, new_dir));
-            }
-            Some(Position::RightMirror) => {
-                let new_dir = match dir {
-                    Direction::Up => Direction::Left,
-                    Direction::Down => Direction::Right,
-                    Direction::Left => Direction::Up,
-                    Direction::Right => Direction::Down,
-                };
-                stack.push((new_dir + pos, new_dir));
-            }
-            Some(Position::VericalSplitter) => {
+    }
+
+    fn len(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+}
+
+impl Position {
+    fn glyph(&self) -> char {
+        match self {
+            Position::Empty => '.',
+            Position::LeftMirror => '/',
+            Position::RightMirror => '\\',
+            Position::VericalSplitter => '|',
+            Position::HoritzontalSplitter => '-',
+        }
+    }
+
+    /// The directions a beam leaves this tile in when entering it travelling `dir`.
+    fn outputs(&self, dir: Direction) -> Vec<Direction> {
+        match self {
+            Position::Empty => vec![dir],
+            Position::LeftMirror => vec![match dir {
+                Direction::Up => Direction::Right,
+                Direction::Down => Direction::Left,
+                Direction::Left => Direction::Down,
+                Direction::Right => Direction::Up,
+            }],
+            Position::RightMirror => vec![match dir {
+                Direction::Up => Direction::Left,
+                Direction::Down => Direction::Right,
+                Direction::Left => Direction::Up,
+                Direction::Right => Direction::Down,
+            }],
+            Position::VericalSplitter => {
                 if dir == Direction::Left || dir == Direction::Right {
-                    stack.push((Direction::Up + pos, Direction::Up));
-                    stack.push((Direction::Down + pos, Direction::Down));
+                    vec![Direction::Up, Direction::Down]
                 } else {
-                    stack.push((dir + pos, dir));
+                    vec![dir]
                 }
             }
-            Some(Position::HoritzontalSplitter) => {
+            Position::HoritzontalSplitter => {
                 if dir == Direction::Up || dir == Direction::Down {
-                    stack.push((Direction::Left + pos, Direction::Left));
-                    stack.push((Direction::Right + pos, Direction::Right));
+                    vec![Direction::Left, Direction::Right]
                 } else {
-                    stack.push((dir + pos, dir));
+                    vec![dir]
+                }
+            }
+        }
+    }
+}
+
+/// Precomputes the tiles energised by every beam leaving an optical element, so that the tiles
+/// energised by any entry beam only need one straight walk and a few unions.
+///
+/// Each beam travels in a straight segment until it hits an element that turns or splits it.
+/// The segments form a graph between beams, which can have cycles through splitters, so the
+/// strongly connected components are condensed and the energised tiles are built up from the
+/// last component backwards.
+struct BeamEngine<'a> {
+    grid: &'a Grid,
+    energised: HashMap<Beam, TileSet>,
+}
+
+impl<'a> BeamEngine<'a> {
+    fn new(grid: &'a Grid) -> Self {
+        let mut engine = BeamEngine {
+            grid,
+            energised: HashMap::new(),
+        };
+
+        // Every beam that can leave an element, plus every beam entering from the edge.
+        let mut beams = grid.starting_positions();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, pos) in row.iter().enumerate() {
+                if !matches!(pos, Position::Empty) {
+                    for dir in [
+                        Direction::Up,
+                        Direction::Down,
+                        Direction::Left,
+                        Direction::Right,
+                    ] {
+                        beams.push((dir + (x as isize, y as isize), dir));
+                    }
+                }
+            }
+        }
+        beams.retain(|&((x, y), _)| grid.get(x, y).is_some());
+
+        let mut graph: GraphMap<Beam, (), Directed> = GraphMap::new();
+        let mut segments = HashMap::new();
+        for beam in beams {
+            let (tiles, next) = engine.segment(beam);
+            graph.add_node(beam);
+            for next_beam in next {
+                graph.add_edge(beam, next_beam, ());
+            }
+            segments.insert(beam, tiles);
+        }
+
+        // Tarjan's algorithm returns the components in reverse topological order, so every
+        // component a beam leads to has already been filled in.
+        for component in tarjan_scc(&graph) {
+            let mut tiles = TileSet::new(grid.width(), grid.height());
+            for beam in &component {
+                tiles.union_with(&segments[beam]);
+                for next_beam in graph.neighbors(*beam) {
+                    if let Some(next_tiles) = engine.energised.get(&next_beam) {
+                        tiles.union_with(next_tiles);
+                    }
+                }
+            }
+            for beam in component {
+                engine.energised.insert(beam, tiles.clone());
+            }
+        }
+
+        engine
+    }
+
+    /// Follows a beam in a straight line until it leaves the grid or hits an element that
+    /// turns it, returning the tiles passed over and the beams that leave the element.
+    fn segment(&self, (mut pos, dir): Beam) -> (TileSet, Vec<Beam>) {
+        let mut tiles = TileSet::new(self.grid.width(), self.grid.height());
+
+        while let Some(position) = self.grid.get(pos.0, pos.1) {
+            tiles.insert(pos);
+            let outputs = position.outputs(dir);
+            if outputs != [dir] {
+                let next = outputs
+                    .into_iter()
+                    .map(|new_dir| (new_dir + pos, new_dir))
+                    .filter(|&((x, y), _)| self.grid.get(x, y).is_some())
+                    .collect();
+                return (tiles, next);
+            }
+            pos = dir + pos;
+        }
+
+        (tiles, Vec::new())
+    }
+
+    /// The tiles energised by a beam entering the grid anywhere.
+    fn energised(&self, beam: Beam) -> TileSet {
+        if let Some(tiles) = self.energised.get(&beam) {
+            return tiles.clone();
+        }
+        let (mut tiles, next) = self.segment(beam);
+        for next_beam in next {
+            tiles.union_with(&self.energised[&next_beam]);
+        }
+        tiles
+    }
+}
+
+impl Grid {
+    /// Every beam state reached from `start`, found by stepping the beams one tile at a time.
+    pub fn trace(&self, start: Beam) -> HashSet<Beam> {
+        let mut seen = HashSet::new();
+        let mut beams = vec![start];
+        while let Some((pos, dir)) = beams.pop() {
+            let Some(position) = self.get(pos.0, pos.1) else {
+                continue;
+            };
+            if !seen.insert((pos, dir)) {
+                continue;
+            }
+            for new_dir in position.outputs(dir) {
+                beams.push((new_dir + pos, new_dir));
+            }
+        }
+        seen
+    }
+
+    /// The grid with every tile energised by `start` marked `#`.
+    pub fn render_energised(&self, start: Beam) -> String {
+        let tiles = BeamEngine::new(self).energised(start);
+
+        let mut output = String::new();
+        for y in 0..self.height() as isize {
+            for x in 0..self.width() as isize {
+                output.push(if tiles.contains((x, y)) { '#' } else { '.' });
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// The grid with the beams from `start` drawn over the empty tiles, as an arrow when one
+    /// direction passes through a tile and as the number of directions otherwise.
+    pub fn render_directions(&self, start: Beam) -> String {
+        let mut directions = vec![vec![Vec::new(); self.width()]; self.height()];
+        for ((x, y), dir) in self.trace(start) {
+            directions[y as usize][x as usize].push(dir);
+        }
+
+        let mut output = String::new();
+        for (row, row_directions) in self.iter().zip(directions) {
+            for (pos, dirs) in row.iter().zip(row_directions) {
+                output.push(match (pos, &dirs[..]) {
+                    (Position::Empty, [dir]) => dir.arrow(),
+                    (Position::Empty, [_, ..]) => char::from_digit(dirs.len() as u32, 10).unwrap(),
+                    _ => pos.glyph(),
+                });
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// How many of the edge entry beams energise each tile.
+    pub fn heat_map(&self) -> Vec<Vec<usize>> {
+        let engine = BeamEngine::new(self);
+
+        let mut heat = vec![vec![0; self.width()]; self.height()];
+        for start in self.starting_positions() {
+            let tiles = engine.energised(start);
+            for (y, row) in heat.iter_mut().enumerate() {
+                for (x, count) in row.iter_mut().enumerate() {
+                    if tiles.contains((x as isize, y as isize)) {
+                        *count += 1;
+                    }
                 }
             }
-            None => {}
         }
+        heat
+    }
+
+    /// The heat map as text, shading each tile relative to the hottest tile.
+    pub fn render_heat_map(&self) -> String {
+        const SHADES: &[u8] = b" .:-=+*#%@";
+
+        let heat = self.heat_map();
+        let max = heat.iter().flatten().copied().max().unwrap_or(0).max(1);
+
+        let mut output = String::new();
+        for row in heat {
+            for count in row {
+                output.push(SHADES[count * (SHADES.len() - 1) / max] as char);
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// The heat map as a binary PPM image with one pixel per tile, going from black through red
+    /// and yellow to white as more entry beams energise a tile.
+    pub fn heat_map_ppm(&self) -> Vec<u8> {
+        let heat = self.heat_map();
+        let max = heat.iter().flatten().copied().max().unwrap_or(0).max(1);
+
+        let mut output = format!("P6\n{} {}\n255\n", self.width(), self.height()).into_bytes();
+        for count in heat.into_iter().flatten() {
+            let level = count * 3 * 255 / max;
+            output.extend([0, 255, 510].map(|offset| level.saturating_sub(offset).min(255) as u8));
+        }
+        output
     }
+}
+
+pub fn star_one(mut input: impl BufRead) -> String {
+    let mut str = String::new();
+    input.read_to_string(&mut str).unwrap();
 
-    let tiles_covered = seen
-        .into_iter()
-        .map(|(pos, _dir)| pos)
-        .collect::<HashSet<_>>();
-
-    // for (i, row) in map.iter().enumerate() {
-    //     for (j, _c) in row.iter().enumerate() {
-    //         print!(
-    //             "{}",
-    //             if tiles_covered.contains(&(j as isize, i as isize)) {
-    //                 '#'
-    //             } else {
-    //                 '.'
-    //             }
-    //         );
-    //     }
-    //     println!("");
-    // }
-
-    tiles_covered
-        .into_iter()
-        .filter(|pos| map.get(pos.0, pos.1).is_some())
-        .count()
+    let map = str.parse::<Grid>().unwrap();
+
+    BeamEngine::new(&map)
+        .energised(((0, 0), Direction::Right))
+        .len()
         .to_string()
 }
 
@@ -202,86 +444,11 @@ pub fn star_two(mut input: impl BufRead) -> String {
     input.read_to_string(&mut str).unwrap();
 
     let map = str.parse::<Grid>().unwrap();
+    let engine = BeamEngine::new(&map);
 
-    let starting = map.starting_positions();
-    starting
-        .into_iter()
-        .map(|(pos, dir)| {
-            let mut seen: HashSet<((isize, isize), Direction)> = HashSet::new();
-            let mut stack = Vec::new();
-            stack.push((pos, dir));
-
-            while let Some((pos, dir)) = stack.pop() {
-                if seen.contains(&(pos, dir)) {
-                    continue;
-                }
-                seen.insert((pos, dir));
-                match map.get(pos.0, pos.1) {
-                    Some(Position::Empty) => {
-                        stack.push((dir + pos, dir));
-                    }
-                    Some(Position::LeftMirror) => {
-                        let new_dir = match dir {
-                            Direction::Up => Direction::Right,
-                            Direction::Down => Direction::Left,
-                            Direction::Left => Direction::Down,
-                            Direction::Right => Direction::Up,
-                        };
-                        stack.push((new_dir + pos, new_dir));
-                    }
-                    Some(Position::RightMirror) => {
-                        let new_dir = match dir {
-                            Direction::Up => Direction::Left,
-                            Direction::Down => Direction::Right,
-                            Direction::Left => Direction::Up,
-                            Direction::Right => Direction::Down,
-                        };
-                        stack.push((new_dir + pos, new_dir));
-                    }
-                    Some(Position::VericalSplitter) => {
-                        if dir == Direction::Left || dir == Direction::Right {
-                            stack.push((Direction::Up + pos, Direction::Up));
-                            stack.push((Direction::Down + pos, Direction::Down));
-                        } else {
-                            stack.push((dir + pos, dir));
-                        }
-                    }
-                    Some(Position::HoritzontalSplitter) => {
-                        if dir == Direction::Up || dir == Direction::Down {
-                            stack.push((Direction::Left + pos, Direction::Left));
-                            stack.push((Direction::Right + pos, Direction::Right));
-                        } else {
-                            stack.push((dir + pos, dir));
-                        }
-                    }
-                    None => {}
-                }
-            }
-
-            let tiles_covered = seen
-                .into_iter()
-                .map(|(pos, _dir)| pos)
-                .collect::<HashSet<_>>();
-
-            // for (i, row) in map.iter().enumerate() {
-            //     for (j, _c) in row.iter().enumerate() {
-            //         print!(
-            //             "{}",
-            //             if tiles_covered.contains(&(j as isize, i as isize)) {
-            //                 '#'
-            //             } else {
-            //                 '.'
-            //             }
-            //         );
-            //     }
-            //     println!("");
-            // }
-
-            tiles_covered
-                .into_iter()
-                .filter(|pos| map.get(pos.0, pos.1).is_some())
-                .count()
-        })
+    map.starting_positions()
+        .into_par_iter()
+        .map(|beam| engine.energised(beam).len())
         .max()
         .unwrap()
         .to_string()
@@ -290,7 +457,8 @@ pub fn star_two(mut input: impl BufRead) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Cursor;
+    use crate::test_util::Lcg;
+    use std::{collections::HashSet, io::Cursor};
 
     #[test]
     fn test_star_one() {
@@ -329,4 +497,152 @@ mod tests {
             "51"
         );
     }
+
+    fn simulate(grid: &Grid, start: Beam) -> HashSet<(isize, isize)> {
+        grid.trace(start).into_iter().map(|(pos, _)| pos).collect()
+    }
+
+    #[test]
+    fn test_engine_matches_simulation() {
+        let grid = ".|...\\....
+|.-.\\.....
+.....|-...
+........|.
+..........
+.........\\
+..../.\\\\..
+.-.-/..|..
+.|....-|.\\
+..//.|...."
+            .parse::<Grid>()
+            .unwrap();
+        let engine = BeamEngine::new(&grid);
+
+        for beam in grid.starting_positions() {
+            let expected = simulate(&grid, beam);
+            let tiles = engine.energised(beam);
+            assert_eq!(tiles.len(), expected.len(), "{beam:?}");
+            assert!(expected.iter().all(|&pos| tiles.contains(pos)), "{beam:?}");
+        }
+    }
+
+    #[test]
+    fn test_non_square_brute_force() {
+        // Generate small rectangular contraptions and check every edge entry.
+        let mut lcg = Lcg::new(16);
+        let mut random = |n: usize| lcg.below(n);
+
+        for _ in 0..200 {
+            let (width, height) = (1 + random(12), 1 + random(12));
+            let input = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| ['.', '.', '.', '/', '\\', '|', '-'][random(7)])
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let grid = input.parse::<Grid>().unwrap();
+            let engine = BeamEngine::new(&grid);
+
+            let starts = grid.starting_positions();
+            assert_eq!(starts.len(), 2 * (width + height));
+            for beam in starts {
+                let expected = simulate(&grid, beam);
+                let tiles = engine.energised(beam);
+                assert_eq!(tiles.len(), expected.len(), "{input}\n{beam:?}");
+                assert!(expected.iter().all(|&pos| tiles.contains(pos)));
+            }
+
+            let best = grid
+                .starting_positions()
+                .into_iter()
+                .map(|beam| simulate(&grid, beam).len())
+                .max()
+                .unwrap();
+            assert_eq!(star_two(Cursor::new(input)), best.to_string());
+        }
+    }
+
+    #[test]
+    fn test_ragged_grid() {
+        assert!("..\n...".parse::<Grid>().is_err());
+    }
+
+    const EXAMPLE: &str = ".|...\\....
+|.-.\\.....
+.....|-...
+........|.
+..........
+.........\\
+..../.\\\\..
+.-.-/..|..
+.|....-|.\\
+..//.|....";
+
+    #[test]
+    fn test_render_energised() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+        assert_eq!(
+            grid.render_energised(((0, 0), Direction::Right)),
+            "######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#..
+"
+        );
+    }
+
+    #[test]
+    fn test_render_directions() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+        assert_eq!(
+            grid.render_directions(((0, 0), Direction::Right)),
+            ">|<<<\\....
+|v-.\\^....
+.v...|->>>
+.v...v^.|.
+.v...v^...
+.v...v^..\\
+.v../2\\\\..
+<->-/vv|..
+.|<<<2-|.\\
+.v//.|.v..
+"
+        );
+    }
+
+    #[test]
+    fn test_heat_map() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+        let heat = grid.heat_map();
+
+        // Each tile's heat is the number of entry beams whose energised set contains it.
+        let starts = grid.starting_positions();
+        for (y, row) in heat.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                let expected = starts
+                    .iter()
+                    .filter(|&&start| simulate(&grid, start).contains(&(x as isize, y as isize)))
+                    .count();
+                assert_eq!(count, expected);
+            }
+        }
+
+        let text = grid.render_heat_map();
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.lines().all(|line| line.chars().count() == 10));
+        assert!(text.contains('@'));
+
+        let ppm = grid.heat_map_ppm();
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 10 * 10 * 3);
+    }
 }
@@ -1,13 +1,20 @@
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
     io::BufRead,
     ops::{Add, Deref},
     str::FromStr,
 };
 
-struct Grid(Vec<Vec<isize>>);
+pub struct Grid(Vec<Vec<isize>>);
 
 impl Grid {
+    fn width(&self) -> usize {
+        self.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.len()
+    }
+
     fn get_heat(&self, pos: &(isize, isize)) -> Option<isize> {
         if pos.0 < 0 || pos.1 < 0 {
             return None;
@@ -25,7 +32,13 @@ impl FromStr for Grid {
         for line in s.lines() {
             let mut row = Vec::new();
             for c in line.chars() {
-                row.push(c.to_digit(10).unwrap() as isize);
+                row.push(c.to_digit(10).ok_or(format!("Invalid heat loss {c}"))? as isize);
+            }
+            if grid
+                .first()
+                .is_some_and(|first: &Vec<isize>| first.len() != row.len())
+            {
+                return Err(format!("Row {} has a different width", grid.len()));
             }
             grid.push(row);
         }
@@ -42,7 +55,7 @@ impl Deref for Grid {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Copy, Hash)]
-enum Direction {
+pub enum Direction {
     Up,
     Down,
     Left,
@@ -50,6 +63,31 @@ enum Direction {
 }
 
 impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn arrow(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
     fn left(&self) -> Self {
         match self {
             Direction::Up => Direction::Left,
@@ -82,94 +120,346 @@ impl Add<(isize, isize)> for Direction {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, PartialOrd, Ord)]
-struct State {
-    heat: isize,
-    pos: (isize, isize),
-    dir: Direction,
-    count_straight: usize,
+/// A single move of the crucible onto `pos`, losing `heat` there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub pos: (isize, isize),
+    pub dir: Direction,
+    pub heat: isize,
 }
 
-fn process(grid: &Grid, min_step: usize, max_step: usize) -> isize {
-    let mut heap = BinaryHeap::new();
-    heap.push(State {
-        heat: 0,
-        pos: (0, 0),
-        dir: Direction::Right,
-        count_straight: 0,
-    });
-    heap.push(State {
-        heat: 0,
-        pos: (0, 0),
-        dir: Direction::Down,
-        count_straight: 0,
-    });
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub heat: isize,
+    pub steps: Vec<Step>,
+}
+
+impl Route {
+    /// The grid with every tile the route enters replaced by the direction it entered in.
+    pub fn render(&self, grid: &Grid) -> String {
+        let mut tiles = grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&heat| char::from_digit(heat as u32, 10).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for step in &self.steps {
+            tiles[step.pos.0 as usize][step.pos.1 as usize] = step.dir.arrow();
+        }
+
+        tiles
+            .into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect()
+    }
+}
 
-    let mut cost_map = HashMap::new();
-    let mut seen = HashSet::new();
+/// Which way the crucible may go relative to the direction of the run it just finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+    Reverse,
+}
 
-    while let Some(State {
-        pos,
-        heat,
-        dir,
-        count_straight,
-    }) = heap.pop()
-    {
-        if pos == (grid.len() as isize - 1, grid[0].len() as isize - 1) {
-            return -heat;
+impl Turn {
+    fn apply(self, dir: Direction) -> Direction {
+        match self {
+            Turn::Left => dir.left(),
+            Turn::Right => dir.right(),
+            Turn::Reverse => dir.opposite(),
         }
+    }
+}
 
-        if seen.contains(&(pos, dir, count_straight)) {
-            continue;
+/// How a crucible may move through the city.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    /// The fewest tiles the crucible must move in a line before turning.
+    pub min_step: usize,
+    /// The most tiles the crucible may move in a line.
+    pub max_step: usize,
+    pub starts: Vec<(isize, isize)>,
+    pub goals: Vec<(isize, isize)>,
+    pub turns: Vec<Turn>,
+    /// The heat lost on each tile is multiplied by the multiplier for the direction moved,
+    /// indexed by `Direction as usize`.
+    pub multipliers: [u32; 4],
+    /// Whether the crucible must also have moved `min_step` tiles in a line to stop at a goal.
+    pub stop_after_min_step: bool,
+}
+
+impl Rules {
+    /// The puzzle's rules: from the top left to the bottom right, turning left or right, and
+    /// stopping at the end only after a full `min_step`.
+    pub fn new(grid: &Grid, min_step: usize, max_step: usize) -> Self {
+        Rules {
+            min_step,
+            max_step,
+            starts: vec![(0, 0)],
+            goals: vec![(grid.height() as isize - 1, grid.width() as isize - 1)],
+            turns: vec![Turn::Left, Turn::Right],
+            multipliers: [1; 4],
+            stop_after_min_step: true,
         }
-        seen.insert((pos, dir, count_straight));
+    }
 
-        if let Some(next_heat) = grid.get_heat(&(dir + pos)) {
-            if count_straight < max_step {
-                let pos = dir + pos;
-                let heat = heat - next_heat;
-                cost_map.insert(pos, -heat);
-                heap.push(State {
-                    heat,
-                    pos,
-                    dir,
-                    count_straight: count_straight + 1,
-                });
+    pub fn with_starts(mut self, starts: Vec<(isize, isize)>) -> Self {
+        self.starts = starts;
+        self
+    }
+
+    pub fn with_goals(mut self, goals: Vec<(isize, isize)>) -> Self {
+        self.goals = goals;
+        self
+    }
+
+    pub fn with_turns(mut self, turns: Vec<Turn>) -> Self {
+        self.turns = turns;
+        self
+    }
+
+    pub fn with_multiplier(mut self, dir: Direction, multiplier: u32) -> Self {
+        self.multipliers[dir as usize] = multiplier;
+        self
+    }
+
+    /// Pass `false` to let the crucible stop at a goal partway through its first `min_step`.
+    pub fn with_stop_after_min_step(mut self, stop_after_min_step: bool) -> Self {
+        self.stop_after_min_step = stop_after_min_step;
+        self
+    }
+}
+
+/// The search state of a crucible that has just finished a straight run, stored as an index of
+/// `(row * width + column) * 4 + direction` into flat arrays.
+type State = usize;
+
+/// Marks a predecessor as the start tile with the given index rather than a state.
+const START: u32 = 1 << 31;
+
+/// A priority queue for small integer priorities that never go below the last popped one, as
+/// with A* using a consistent heuristic.
+#[derive(Default)]
+struct BucketQueue {
+    buckets: Vec<Vec<u32>>,
+    current: usize,
+}
+
+impl BucketQueue {
+    fn push(&mut self, priority: u32, state: State) {
+        let priority = priority as usize;
+        if priority >= self.buckets.len() {
+            self.buckets.resize_with(priority + 1, Vec::new);
+        }
+        self.buckets[priority].push(state as u32);
+    }
+
+    fn pop(&mut self) -> Option<(u32, State)> {
+        while self.current < self.buckets.len() {
+            if let Some(state) = self.buckets[self.current].pop() {
+                return Some((self.current as u32, state as State));
             }
+            // Drained buckets are never used again, so give their memory back.
+            self.buckets[self.current] = Vec::new();
+            self.current += 1;
         }
+        None
+    }
+}
 
-        if let Some(next_heat) = grid.get_heat(&(dir.left() + pos)) {
-            if count_straight >= min_step {
-                let pos = dir.left() + pos;
-                let heat = heat - next_heat;
+/// A* over the ends of straight runs, so that the number of steps taken in a line never needs
+/// to be part of the state.
+struct Search<'a> {
+    grid: &'a Grid,
+    rules: &'a Rules,
+    heat: Vec<u32>,
+    goals: Vec<bool>,
+    /// The rows and columns spanned by the goals, as `(top, bottom, left, right)`.
+    goal_bounds: (isize, isize, isize, isize),
+    min_heat: u32,
+    best: Vec<u32>,
+    previous: Vec<u32>,
+    queue: BucketQueue,
+}
 
-                cost_map.insert(pos, -heat);
+impl<'a> Search<'a> {
+    fn new(grid: &'a Grid, rules: &'a Rules, goals: &[(isize, isize)]) -> Self {
+        let states = grid.width() * grid.height() * 4;
+        let mut goal_tiles = vec![false; grid.width() * grid.height()];
+        for &(row, column) in goals {
+            goal_tiles[row as usize * grid.width() + column as usize] = true;
+        }
+        let goal_bounds = (
+            goals.iter().map(|goal| goal.0).min().unwrap(),
+            goals.iter().map(|goal| goal.0).max().unwrap(),
+            goals.iter().map(|goal| goal.1).min().unwrap(),
+            goals.iter().map(|goal| goal.1).max().unwrap(),
+        );
 
-                heap.push(State {
-                    heat,
-                    pos,
-                    dir: dir.left(),
-                    count_straight: 1,
-                });
-            }
+        Search {
+            grid,
+            rules,
+            heat: grid.iter().flatten().map(|&heat| heat as u32).collect(),
+            goals: goal_tiles,
+            goal_bounds,
+            min_heat: grid.iter().flatten().copied().min().unwrap_or(0) as u32
+                * rules.multipliers.iter().copied().min().unwrap(),
+            best: vec![u32::MAX; states],
+            previous: vec![u32::MAX; states],
+            queue: BucketQueue::default(),
         }
+    }
 
-        if let Some(next_heat) = grid.get_heat(&(dir.right() + pos)) {
-            if count_straight >= min_step {
-                let pos = dir.right() + pos;
-                cost_map.insert(pos, -(heat - next_heat));
+    fn position(&self, state: State) -> (isize, isize) {
+        let tile = state / 4;
+        (
+            (tile / self.grid.width()) as isize,
+            (tile % self.grid.width()) as isize,
+        )
+    }
+
+    /// The Manhattan distance to the nearest tile in the goals' bounding box, scaled by the
+    /// cheapest possible move.
+    fn estimate(&self, (row, column): (isize, isize)) -> u32 {
+        let (top, bottom, left, right) = self.goal_bounds;
+        let rows = (top - row).max(row - bottom).max(0);
+        let columns = (left - column).max(column - right).max(0);
+        (rows + columns) as u32 * self.min_heat
+    }
 
-                heap.push(State {
-                    heat: heat - next_heat,
+    /// Follows a run from `pos` having lost `heat`, relaxing every state it can stop in. Unless
+    /// the rules say otherwise, the crucible may also stop at a goal before it has moved
+    /// `min_step` tiles.
+    fn relax(&mut self, from: u32, pos: (isize, isize), mut heat: u32, dir: Direction) {
+        let width = self.grid.width();
+        let (row, column) = (pos.0 as usize, pos.1 as usize);
+        let (room, stride) = match dir {
+            Direction::Up => (row, -(width as isize)),
+            Direction::Down => (self.grid.height() - 1 - row, width as isize),
+            Direction::Left => (column, -1),
+            Direction::Right => (width - 1 - column, 1),
+        };
+        let multiplier = self.rules.multipliers[dir as usize];
+
+        let mut tile = row * width + column;
+        let mut next = pos;
+        for steps in 1..=self.rules.max_step.min(room) {
+            tile = tile.wrapping_add_signed(stride);
+            next = dir + next;
+            heat += self.heat[tile] * multiplier;
+            if steps < self.rules.min_step && (self.rules.stop_after_min_step || !self.goals[tile])
+            {
+                continue;
+            }
+            let next_state = tile * 4 + dir as usize;
+            if heat < self.best[next_state] {
+                self.best[next_state] = heat;
+                self.previous[next_state] = from;
+                self.queue.push(heat + self.estimate(next), next_state);
+            }
+        }
+    }
+
+    /// Walks the predecessors back from `state` to a start, expanding each run into steps.
+    fn route(&self, mut state: State) -> Route {
+        let mut steps = Vec::new();
+        loop {
+            let before = self.previous[state];
+            let start = if before & START != 0 {
+                let tile = (before & !START) as usize;
+                self.position(tile * 4)
+            } else {
+                self.position(before as State)
+            };
+            let dir = Direction::ALL[state % 4];
+            let multiplier = self.rules.multipliers[dir as usize] as isize;
+            let mut pos = self.position(state);
+            while pos != start {
+                steps.push(Step {
                     pos,
-                    dir: dir.right(),
-                    count_straight: 1,
+                    dir,
+                    heat: self.grid.get_heat(&pos).unwrap() * multiplier,
                 });
+                pos = dir.opposite() + pos;
+            }
+            if before & START != 0 {
+                break;
             }
+            state = before as State;
+        }
+        steps.reverse();
+
+        Route {
+            heat: steps.iter().map(|step| step.heat).sum(),
+            steps,
+        }
+    }
+}
+
+/// Finds the route with the least heat loss from any start to any goal. Starts and goals
+/// outside the grid are ignored.
+pub fn find_route(grid: &Grid, rules: &Rules) -> Option<Route> {
+    let in_grid = |&&(row, column): &&(isize, isize)| {
+        (0..grid.height() as isize).contains(&row) && (0..grid.width() as isize).contains(&column)
+    };
+    let starts = rules
+        .starts
+        .iter()
+        .filter(in_grid)
+        .copied()
+        .collect::<Vec<_>>();
+    let goals = rules
+        .goals
+        .iter()
+        .filter(in_grid)
+        .copied()
+        .collect::<Vec<_>>();
+    if starts.is_empty() || goals.is_empty() {
+        return None;
+    }
+    if starts.iter().any(|start| goals.contains(start)) {
+        return Some(Route {
+            heat: 0,
+            steps: Vec::new(),
+        });
+    }
+
+    let mut search = Search::new(grid, rules, &goals);
+    for (row, column) in starts {
+        let tile = row as usize * grid.width() + column as usize;
+        for dir in Direction::ALL {
+            search.relax(START | tile as u32, (row, column), 0, dir);
+        }
+    }
+
+    while let Some((priority, current)) = search.queue.pop() {
+        // Skip entries superseded by a cheaper way into the same state.
+        let pos = search.position(current);
+        let heat = search.best[current];
+        if priority != heat + search.estimate(pos) {
+            continue;
+        }
+        if search.goals[current / 4] {
+            return Some(search.route(current));
+        }
+
+        let dir = Direction::ALL[current % 4];
+        for &turn in &rules.turns {
+            search.relax(current as u32, pos, heat, turn.apply(dir));
         }
     }
 
-    unreachable!()
+    None
+}
+
+/// Finds the route with the least heat loss from the top left to the bottom right, where the
+/// crucible must move at least `min_step` tiles before turning and at most `max_step` in a line.
+/// As with the ultra crucible, it must also move `min_step` tiles before it can stop at the end.
+pub fn process(grid: &Grid, min_step: usize, max_step: usize) -> Option<Route> {
+    find_route(grid, &Rules::new(grid, min_step, max_step))
 }
 
 pub fn star_one(mut input: impl BufRead) -> String {
@@ -178,7 +468,7 @@ pub fn star_one(mut input: impl BufRead) -> String {
 
     let grid = str.parse::<Grid>().unwrap();
 
-    process(&grid, 1, 3).to_string()
+    process(&grid, 1, 3).unwrap().heat.to_string()
 }
 
 pub fn star_two(mut input: impl BufRead) -> String {
@@ -187,13 +477,14 @@ pub fn star_two(mut input: impl BufRead) -> String {
 
     let grid = str.parse::<Grid>().unwrap();
 
-    process(&grid, 4, 10).to_string()
+    process(&grid, 4, 10).unwrap().heat.to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Cursor;
+    use crate::test_util::Lcg;
+    use std::{cmp::Reverse, collections::BinaryHeap, io::Cursor};
 
     #[test]
     fn test_star_one() {
@@ -238,4 +529,237 @@ mod tests {
             "94"
         );
     }
+
+    const EXAMPLE: &str = "2413432311323
+3215453535623
+3255245654254
+3446585845452
+4546657867536
+1438598798454
+4457876987766
+3637877979653
+4654967986887
+4564679986453
+1224686865563
+2546548887735
+4322674655533";
+
+    fn check_route(grid: &Grid, route: &Route, rules: &Rules) {
+        assert_eq!(route.heat, route.steps.iter().map(|step| step.heat).sum());
+        let Some(last) = route.steps.last() else {
+            return;
+        };
+        assert!(rules.goals.contains(&last.pos));
+
+        let first = &route.steps[0];
+        let mut pos = first.dir.opposite() + first.pos;
+        assert!(rules.starts.contains(&pos));
+        let mut run = 0;
+        for (i, step) in route.steps.iter().enumerate() {
+            assert_eq!(step.dir + pos, step.pos);
+            assert_eq!(
+                Some(step.heat),
+                grid.get_heat(&step.pos)
+                    .map(|heat| heat * rules.multipliers[step.dir as usize] as isize)
+            );
+            let last_dir = i.checked_sub(1).map(|i| route.steps[i].dir);
+            if last_dir.is_some_and(|last_dir| step.dir != last_dir) {
+                assert!(run >= rules.min_step);
+                let last_dir = last_dir.unwrap();
+                assert!(rules
+                    .turns
+                    .iter()
+                    .any(|turn| turn.apply(last_dir) == step.dir));
+                run = 0;
+            }
+            run += 1;
+            assert!(run <= rules.max_step);
+            pos = step.pos;
+        }
+        if rules.stop_after_min_step {
+            assert!(run >= rules.min_step);
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+
+        let route = process(&grid, 1, 3).unwrap();
+        assert_eq!(route.heat, 102);
+        check_route(&grid, &route, &Rules::new(&grid, 1, 3));
+
+        let route = process(&grid, 4, 10).unwrap();
+        assert_eq!(route.heat, 94);
+        check_route(&grid, &route, &Rules::new(&grid, 4, 10));
+    }
+
+    #[test]
+    fn test_render_route() {
+        let grid = "11111
+99991
+99991"
+            .parse::<Grid>()
+            .unwrap();
+        let route = process(&grid, 1, 3).unwrap();
+        assert_eq!(route.heat, 14);
+        assert_eq!(
+            route.render(&grid),
+            "1>>>1
+999v>
+9999v
+"
+        );
+    }
+
+    /// Dijkstra over single tile moves, tracking how far the crucible has gone in a line.
+    fn reference(grid: &Grid, rules: &Rules) -> Option<isize> {
+        use std::collections::HashSet;
+
+        let mut heap = BinaryHeap::new();
+        for &start in &rules.starts {
+            if grid.get_heat(&start).is_some() {
+                for dir in Direction::ALL {
+                    heap.push(Reverse((0, start, dir, 0)));
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        while let Some(Reverse((heat, pos, dir, count))) = heap.pop() {
+            if rules.goals.contains(&pos)
+                && (count >= rules.min_step || !rules.stop_after_min_step || count == 0)
+            {
+                return Some(heat);
+            }
+            if !seen.insert((pos, dir, count)) {
+                continue;
+            }
+            let mut moves = Vec::new();
+            if count < rules.max_step {
+                moves.push((dir, count + 1));
+            }
+            if count >= rules.min_step && count > 0 {
+                for turn in &rules.turns {
+                    moves.push((turn.apply(dir), 1));
+                }
+            }
+            for (dir, count) in moves {
+                if let Some(next_heat) = grid.get_heat(&(dir + pos)) {
+                    let next_heat = next_heat * rules.multipliers[dir as usize] as isize;
+                    heap.push(Reverse((heat + next_heat, dir + pos, dir, count)));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_single_block() {
+        let grid = "5".parse::<Grid>().unwrap();
+        assert_eq!(process(&grid, 4, 10).unwrap().heat, 0);
+        assert_eq!(reference(&grid, &Rules::new(&grid, 4, 10)), Some(0));
+    }
+
+    #[test]
+    fn test_stop_after_min_step() {
+        let grid = "111111111111
+999999999991
+999999999991
+999999999991
+999999999991"
+            .parse::<Grid>()
+            .unwrap();
+        let rules = Rules::new(&grid, 4, 10);
+        let route = find_route(&grid, &rules).unwrap();
+        assert_eq!(route.heat, 71);
+        check_route(&grid, &route, &rules);
+        assert_eq!(process(&grid, 4, 10).unwrap().heat, 71);
+
+        let rules = rules.with_stop_after_min_step(false);
+        assert_eq!(find_route(&grid, &rules).unwrap().heat, 47);
+    }
+
+    #[test]
+    fn test_u_turns() {
+        let grid = "1111111".parse::<Grid>().unwrap();
+        let rules = Rules::new(&grid, 1, 3);
+        assert_eq!(find_route(&grid, &rules), None);
+
+        let rules = rules.with_turns(vec![Turn::Left, Turn::Right, Turn::Reverse]);
+        let route = find_route(&grid, &rules).unwrap();
+        assert_eq!(route.heat, 10);
+        check_route(&grid, &route, &rules);
+    }
+
+    #[test]
+    fn test_starts_goals_and_multipliers() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+        let rules = Rules::new(&grid, 1, 3)
+            .with_starts(vec![(12, 0), (0, 12), (20, 20)])
+            .with_goals(vec![(6, 6), (0, 0)])
+            .with_multiplier(Direction::Up, 3);
+        let route = find_route(&grid, &rules).unwrap();
+        assert_eq!(Some(route.heat), reference(&grid, &rules));
+        check_route(&grid, &route, &rules);
+
+        let rules = rules.with_goals(vec![(12, 0)]);
+        assert_eq!(find_route(&grid, &rules).unwrap().steps, Vec::new());
+    }
+
+    #[test]
+    fn test_random_cities() {
+        let mut lcg = Lcg::new(17);
+        let mut random = |n: usize| lcg.below(n);
+
+        for _ in 0..100 {
+            let (width, height) = (1 + random(15), 1 + random(15));
+            let input = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| (b'1' + random(9) as u8) as char)
+                        .collect()
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            let grid = input.parse::<Grid>().unwrap();
+            let tile = |random: &mut dyn FnMut(usize) -> usize| {
+                (random(height) as isize, random(width) as isize)
+            };
+
+            for (min_step, max_step) in [(1, 3), (4, 10), (2, 5)] {
+                let rules = Rules::new(&grid, min_step, max_step);
+                let route = find_route(&grid, &rules);
+                assert_eq!(
+                    route.as_ref().map(|route| route.heat),
+                    reference(&grid, &rules)
+                );
+                if let Some(route) = route {
+                    check_route(&grid, &route, &rules);
+                }
+
+                let mut rules = rules
+                    .with_starts((0..1 + random(3)).map(|_| tile(&mut random)).collect())
+                    .with_goals((0..1 + random(3)).map(|_| tile(&mut random)).collect())
+                    .with_turns(
+                        [Turn::Left, Turn::Right, Turn::Reverse]
+                            .into_iter()
+                            .filter(|_| random(3) > 0)
+                            .collect(),
+                    )
+                    .with_stop_after_min_step(random(2) == 0);
+                for dir in Direction::ALL {
+                    rules = rules.with_multiplier(dir, 1 + random(3) as u32);
+                }
+                let route = find_route(&grid, &rules);
+                assert_eq!(
+                    route.as_ref().map(|route| route.heat),
+                    reference(&grid, &rules),
+                    "{input}\n{rules:?}"
+                );
+                if let Some(route) = route {
+                    check_route(&grid, &route, &rules);
+                }
+            }
+        }
+    }
 }
@@ -1,200 +1,186 @@
-use std::{collections::HashMap, io::BufRead, str::FromStr};
+use std::{io::BufRead, str::FromStr};
 
-use ahash::RandomState;
+use crate::polygon::{Direction, Polygon};
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl From<char> for Direction {
-    fn from(value: char) -> Self {
-        match value {
-            'U' => Self::Up,
-            'D' => Self::Down,
-            'L' => Self::Left,
-            'R' => Self::Right,
-            _ => panic!(),
-        }
-    }
-}
-
-struct Instuction {
+struct Instruction {
     direction: Direction,
-    distance: u32,
+    distance: u64,
+    colour: u32,
 }
 
-impl FromStr for Instuction {
-    type Err = ();
+impl FromStr for Instruction {
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut s = s.split(' ');
-        let direction = s.next().unwrap().chars().next().unwrap().into();
-        let distance = s.next().unwrap().parse().unwrap();
+        let mut parts = s.split(' ');
+        let (Some(direction), Some(distance), Some(colour)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("Invalid instruction {s}"));
+        };
+
+        let direction = match direction {
+            "U" => Direction::Up,
+            "D" => Direction::Down,
+            "L" => Direction::Left,
+            "R" => Direction::Right,
+            x => return Err(format!("Unknown direction {x}")),
+        };
+        let distance = distance
+            .parse()
+            .map_err(|e| format!("Invalid distance {distance}: {e}"))?;
+        let colour = colour
+            .strip_prefix("(#")
+            .and_then(|colour| colour.strip_suffix(')'))
+            .filter(|colour| colour.len() == 6)
+            .and_then(|colour| u32::from_str_radix(colour, 16).ok())
+            .ok_or(format!("Invalid colour {colour}"))?;
 
         Ok(Self {
             direction,
             distance,
+            colour,
         })
     }
 }
 
-enum Position {
-    Trench,
-    Lagoon,
+impl Instruction {
+    /// The move hidden in the colour: five hex digits of distance then one of direction.
+    fn decode_colour(&self) -> Result<(Direction, u64), String> {
+        let direction = match self.colour & 0xf {
+            0 => Direction::Right,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            3 => Direction::Up,
+            x => {
+                return Err(format!(
+                    "Unknown direction {x} in colour {:06x}",
+                    self.colour
+                ))
+            }
+        };
+        Ok((direction, (self.colour >> 4) as u64))
+    }
 }
 
-// fn is_lagoon(
-//     grid: &HashMap<(i32, i32), u32>,
-//     pos: (i32, i32),
-//     min: (isize, isize),
-//     max: (isize, isize),
-// ) -> bool {
-//     let mut neighbours = Vec::new();
-
-//     neighbours.into_iter().all(|x| x.is_some())
-// }
-
-pub fn star_one(mut input: impl BufRead) -> String {
-    let mut str = String::new();
-    input.read_to_string(&mut str).unwrap();
-
-    let mut grid = HashMap::new();
-    grid.insert((0, 0), Position::Trench);
-
-    let mut current_pos = (0, 0);
+/// The dig plan, read either as written or with the moves hidden in the colours.
+pub struct DigPlan {
+    instructions: Vec<Instruction>,
+}
 
-    for ins in str.lines().map(|line| line.parse::<Instuction>().unwrap()) {
-        for _ in 0..ins.distance {
-            match ins.direction {
-                Direction::Up => current_pos.1 += 1,
-                Direction::Down => current_pos.1 -= 1,
-                Direction::Left => current_pos.0 -= 1,
-                Direction::Right => current_pos.0 += 1,
-            }
+impl FromStr for DigPlan {
+    type Err = String;
 
-            grid.insert(current_pos, Position::Trench);
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(DigPlan {
+            instructions: s
+                .lines()
+                .map(|line| line.parse())
+                .collect::<Result<_, _>>()?,
+        })
     }
+}
 
-    // get max x and y
-    let max_x = *grid.keys().map(|(x, _)| x).max().unwrap();
-    let min_x = *grid.keys().map(|(x, _)| x).min().unwrap();
-    // let max_y = *grid.keys().map(|(_, y)| y).max().unwrap();
-    let min_y = *grid.keys().map(|(_, y)| y).min().unwrap();
-
-    let mut stack = Vec::new();
+impl DigPlan {
+    /// The moves of the plan, decoded from the colours if `decode` is set.
+    fn moves(&self, decode: bool) -> Result<Vec<(Direction, u64)>, String> {
+        self.instructions
+            .iter()
+            .map(|ins| {
+                if decode {
+                    ins.decode_colour()
+                } else {
+                    Ok((ins.direction, ins.distance))
+                }
+            })
+            .collect()
+    }
 
-    for x in min_x..=max_x {
-        if matches!(grid.get(&(x, min_y)), Some(Position::Trench))
-            && !grid.contains_key(&(x, min_y + 1))
-        {
-            grid.insert((x, min_y + 1), Position::Lagoon);
-            stack.push((x, min_y + 2));
-        }
+    pub fn lagoon(&self, decode: bool) -> Result<Polygon, String> {
+        Polygon::from_moves(self.moves(decode)?).map_err(|e| e.to_string())
     }
 
-    while let Some(pos) = stack.pop() {
-        if matches!(
-            grid.get(&pos),
-            Some(Position::Lagoon) | Some(Position::Trench)
-        ) {
-            continue;
-        }
+    /// Draws the plan as an SVG, with the lagoon filled in and each trench in its colour. The
+    /// drawing is scaled so that its longest side is about `size` pixels.
+    pub fn svg(&self, decode: bool, size: f64) -> Result<String, String> {
+        let lagoon = self.lagoon(decode)?;
+        let vertices = lagoon.vertices();
+
+        let min_x = vertices.iter().map(|v| v.0).min().unwrap();
+        let max_x = vertices.iter().map(|v| v.0).max().unwrap();
+        let min_y = vertices.iter().map(|v| v.1).min().unwrap();
+        let max_y = vertices.iter().map(|v| v.1).max().unwrap();
+
+        // Each trench is a tile wide, but no thinner than a pixel so it stays visible.
+        let scale = size / ((max_x - min_x).max(max_y - min_y) + 1) as f64;
+        let trench = scale.max(1.0);
+        let width = (max_x - min_x) as f64 * scale + trench;
+        let height = (max_y - min_y) as f64 * scale + trench;
+        // Up is positive y in the plan but down in an SVG.
+        let point = |(x, y): (i128, i128)| {
+            (
+                (x - min_x) as f64 * scale + trench / 2.0,
+                (max_y - y) as f64 * scale + trench / 2.0,
+            )
+        };
 
-        // If any of the neighbours are lagoons, then this is a lagoon
-        if matches!(grid.get(&(pos.0, pos.1 + 1)), Some(Position::Lagoon))
-            || matches!(grid.get(&(pos.0, pos.1 - 1)), Some(Position::Lagoon))
-            || matches!(grid.get(&(pos.0 + 1, pos.1)), Some(Position::Lagoon))
-            || matches!(grid.get(&(pos.0 - 1, pos.1)), Some(Position::Lagoon))
-        {
-            grid.insert(pos, Position::Lagoon);
-            stack.extend(
-                [
-                    (pos.0, pos.1 + 1),
-                    (pos.0, pos.1 - 1),
-                    (pos.0 + 1, pos.1),
-                    (pos.0 - 1, pos.1),
-                ]
-                .into_iter(),
-            );
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" \
+             viewBox=\"0 0 {width:.2} {height:.2}\">\n"
+        );
+        let points = vertices
+            .iter()
+            .map(|&v| {
+                let (x, y) = point(v);
+                format!("{x:.2},{y:.2}")
+            })
+            .collect::<Vec<_>>();
+        svg.push_str(&format!(
+            "  <polygon points=\"{}\" fill=\"#c8c8c8\" stroke=\"none\"/>\n",
+            points.join(" ")
+        ));
+
+        // Zero length moves are dropped from the polygon, so walk the moves again to pair each
+        // trench with its colour.
+        let mut pos = (0, 0);
+        for (ins, (direction, distance)) in self.instructions.iter().zip(self.moves(decode)?) {
+            let distance = distance as i128;
+            let next = match direction {
+                Direction::Up => (pos.0, pos.1 + distance),
+                Direction::Down => (pos.0, pos.1 - distance),
+                Direction::Left => (pos.0 - distance, pos.1),
+                Direction::Right => (pos.0 + distance, pos.1),
+            };
+            let ((x1, y1), (x2, y2)) = (point(pos), point(next));
+            svg.push_str(&format!(
+                "  <line x1=\"{x1:.2}\" y1=\"{y1:.2}\" x2=\"{x2:.2}\" y2=\"{y2:.2}\" \
+                 stroke=\"#{:06x}\" stroke-width=\"{trench:.2}\" stroke-linecap=\"square\"/>\n",
+                ins.colour
+            ));
+            pos = next;
         }
-    }
-
-    grid.into_values()
-        .filter(|p| matches!(p, Position::Lagoon | Position::Trench))
-        .count()
-        .to_string()
-}
 
-struct Instuction2 {
-    direction: Direction,
-    distance: usize,
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
 }
 
-impl FromStr for Instuction2 {
-    type Err = ();
+pub fn star_one(mut input: impl BufRead) -> String {
+    let mut str = String::new();
+    input.read_to_string(&mut str).unwrap();
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut s = s.split(' ');
-        let s = s.next_back().unwrap();
-        let s = s.trim_matches('(').trim_matches(')').trim_matches('#');
-        let value = usize::from_str_radix(&s[..5], 16).unwrap();
-        let direction = match &s[5..] {
-            "0" => Direction::Right,
-            "1" => Direction::Down,
-            "2" => Direction::Left,
-            "3" => Direction::Up,
-            x => panic!("Unknown direction {x} from {s}"),
-        };
-        let distance = value;
+    let plan = str.parse::<DigPlan>().unwrap();
 
-        Ok(Self {
-            direction,
-            distance,
-        })
-    }
+    plan.lagoon(false).unwrap().lattice_points().to_string()
 }
 
 pub fn star_two(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).unwrap();
 
-    let mut grid: HashMap<(isize, isize), Position, RandomState> = HashMap::default();
-    grid.insert((0, 0), Position::Trench);
-
-    let mut current_pos = (0, 0);
-
-    let mut points = vec![(0, 0)];
-    let mut length: isize = 0;
+    let plan = str.parse::<DigPlan>().unwrap();
 
-    for ins in str.lines().map(|line| line.parse::<Instuction2>().unwrap()) {
-        match ins.direction {
-            Direction::Up => current_pos.1 += ins.distance as isize,
-            Direction::Down => current_pos.1 -= ins.distance as isize,
-            Direction::Left => current_pos.0 -= ins.distance as isize,
-            Direction::Right => current_pos.0 += ins.distance as isize,
-        }
-        length += ins.distance as isize;
-        points.push(current_pos);
-    }
-
-    // a_{rea}=\frac{1}{2}\left|\sum_{i=1}^{n-1}\left(x_{1}\left[i\right]y_{1}\left[i+1\right]-y_{1}\left[i\right]x_{1}\left[i+1\right]\right)+x_{1}\left[n\right]y_{1}\left[1\right]-y_{1}\left[n\right]x_{1}\left[1\right]\right|
-    // From https://www.desmos.com/calculator/j6v0dbjzmt
-
-    let area = points
-        .windows(2)
-        .map(|w| w[0].0 * w[1].1 - w[0].1 * w[1].0)
-        .sum::<isize>()
-        + points.last().unwrap().0 * points.first().unwrap().1
-        - points.last().unwrap().1 * points.first().unwrap().0;
-
-    // Have to add length as the width of the perimeter is 1, not sure about the 1 off though.
-    let area = area.abs() / 2 + length / 2 + 1;
-
-    area.to_string()
+    plan.lagoon(true).unwrap().lattice_points().to_string()
 }
 
 #[cfg(test)]
@@ -247,4 +233,59 @@ U 2 (#7a21e3)"
             "952408144115"
         );
     }
+
+    const EXAMPLE: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+    #[test]
+    fn test_invalid_plans() {
+        assert!("R 6 (#70c710)\nX 5 (#0dc571)".parse::<DigPlan>().is_err());
+        assert!("R 6 (70c710)".parse::<DigPlan>().is_err());
+        assert!("R 6".parse::<DigPlan>().is_err());
+
+        let unclosed = "R 6 (#70c710)\nD 5 (#0dc571)".parse::<DigPlan>().unwrap();
+        assert!(unclosed.lagoon(false).is_err());
+        assert!(unclosed.svg(false, 100.0).is_err());
+    }
+
+    /// Every number in the drawing's coordinates.
+    fn coordinates(svg: &str) -> Vec<f64> {
+        svg.split(['"', ' ', ','])
+            .filter_map(|part| part.parse::<f64>().ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_svg() {
+        let plan = EXAMPLE.parse::<DigPlan>().unwrap();
+
+        let svg = plan.svg(false, 100.0).unwrap();
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"70\" height=\"100\""));
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert_eq!(svg.matches("<line").count(), 14);
+        assert!(svg
+            .contains("<line x1=\"5.00\" y1=\"5.00\" x2=\"65.00\" y2=\"5.00\" stroke=\"#70c710\""));
+        assert!(svg.ends_with("</svg>\n"));
+
+        // The decoded plan is far larger, but still has to fit.
+        let svg = plan.svg(true, 500.0).unwrap();
+        assert_eq!(svg.matches("<line").count(), 14);
+        assert!(svg.contains("stroke=\"#7a21e3\""));
+        assert!(coordinates(&svg)
+            .iter()
+            .all(|&c| (0.0..=501.0).contains(&c)));
+    }
 }
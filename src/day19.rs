@@ -1,229 +1,698 @@
-use std::{collections::HashMap, io::BufRead, str::FromStr};
-
-use itertools::Itertools;
-use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::BufRead,
+    str::FromStr,
+};
+
+use lazy_static::lazy_static;
 use regex::Regex;
 
-trait Rule {
-    fn matches(&self, input: &HashMap<String, usize>) -> bool;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
 }
 
-enum Op {
-    LessThan {
-        variable: String,
-        value: usize,
-        output: Id,
-    },
-    GreaterThan {
-        variable: String,
-        value: usize,
-        output: Id,
-    },
-    Identity {
-        output: Id,
-    },
-}
+impl Comparison {
+    /// The comparison that holds exactly when this one doesn't.
+    fn negate(self) -> Self {
+        match self {
+            Comparison::Less => Comparison::GreaterEqual,
+            Comparison::LessEqual => Comparison::Greater,
+            Comparison::Greater => Comparison::LessEqual,
+            Comparison::GreaterEqual => Comparison::Less,
+            Comparison::Equal => Comparison::NotEqual,
+            Comparison::NotEqual => Comparison::Equal,
+        }
+    }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-enum Id {
-    R,
-    A,
-    In,
-    Intermediate(String),
+    fn holds(self, left: u64, right: u64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterEqual => left >= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
 }
 
-impl FromStr for Id {
-    type Err = ();
+impl FromStr for Comparison {
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "R" => Ok(Id::R),
-            "A" => Ok(Id::A),
-            "in" => Ok(Id::In),
-            s => Ok(Id::Intermediate(s.to_string())),
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterEqual),
+            "==" => Ok(Comparison::Equal),
+            "!=" => Ok(Comparison::NotEqual),
+            x => Err(format!("Unknown comparison {x}")),
         }
     }
 }
 
-impl Op {
-    fn output(&self) -> &Id {
-        match self {
-            Op::LessThan { output, .. } => output,
-            Op::GreaterThan { output, .. } => output,
-            Op::Identity { output } => output,
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        })
+    }
+}
+
+/// An inclusive range of attribute values.
+pub type Range = (u64, u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub attribute: String,
+    pub comparison: Comparison,
+    pub value: u64,
+}
+
+impl Condition {
+    fn matches(&self, part: &Part) -> Result<bool, String> {
+        let value = part
+            .get(&self.attribute)
+            .ok_or(format!("Part has no attribute {}", self.attribute))?;
+        Ok(self.comparison.holds(*value, self.value))
+    }
+
+    /// Splits a range into the pieces that do and don't satisfy the condition.
+    fn split(&self, (min, max): Range) -> (Vec<Range>, Vec<Range>) {
+        let v = self.value;
+        // The values below, at and above `v` that lie in the range.
+        let below = (v > min).then(|| (min, max.min(v - 1)));
+        let at = (min..=max).contains(&v).then_some((v, v));
+        let above = (v < max).then(|| (min.max(v + 1), max));
+
+        let (matching, rest) = match self.comparison {
+            Comparison::Less => (vec![below], vec![at, above]),
+            Comparison::LessEqual => (vec![below, at], vec![above]),
+            Comparison::Greater => (vec![above], vec![below, at]),
+            Comparison::GreaterEqual => (vec![at, above], vec![below]),
+            Comparison::Equal => (vec![at], vec![below, above]),
+            Comparison::NotEqual => (vec![below, above], vec![at]),
+        };
+        let merge = |ranges: Vec<Option<Range>>| {
+            let mut merged: Vec<Range> = Vec::new();
+            for (lo, hi) in ranges.into_iter().flatten().filter(|(lo, hi)| lo <= hi) {
+                match merged.last_mut() {
+                    Some(last) if last.1 + 1 == lo => last.1 = hi,
+                    _ => merged.push((lo, hi)),
+                }
+            }
+            merged
+        };
+        (merge(matching), merge(rest))
+    }
+}
+
+impl Condition {
+    pub fn negate(&self) -> Self {
+        Condition {
+            comparison: self.comparison.negate(),
+            ..self.clone()
         }
     }
 }
 
-impl Rule for Op {
-    fn matches(&self, input: &HashMap<String, usize>) -> bool {
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.attribute, self.comparison, self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Accept,
+    Reject,
+    Workflow(String),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(Target::Accept),
+            "R" => Ok(Target::Reject),
+            "" => Err("Missing target".to_string()),
+            s => Ok(Target::Workflow(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Op::LessThan {
-                variable, value, ..
-            } => {
-                let var_value = input.get(variable).unwrap();
-                var_value < value
-            }
-            Op::GreaterThan {
-                variable, value, ..
-            } => {
-                let var_value = input.get(variable).unwrap();
-                var_value > value
-            }
-            Op::Identity { .. } => true,
+            Target::Accept => f.write_str("A"),
+            Target::Reject => f.write_str("R"),
+            Target::Workflow(name) => f.write_str(name),
         }
     }
 }
 
-impl FromStr for Op {
-    type Err = ();
+/// Sends parts matching the condition, or every part if there is none, to the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    condition: Option<Condition>,
+    target: Target,
+}
+
+impl FromStr for Rule {
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let reg = Regex::new(r"^(\w)([><])(\w+):(\w+)$").unwrap();
-        if let Some(capture) = reg.captures(s) {
-            let variable = capture[1].to_string();
-            let op = capture[2].to_string();
-            let value = capture[3]
-                .parse::<usize>()
-                .unwrap_or_else(|_e| panic!("Invalid value: {}", &capture[3]));
-            let output = capture[4].parse().unwrap();
+        lazy_static! {
+            static ref RULE: Regex =
+                Regex::new(r"^([A-Za-z_]\w*)(<=|>=|==|!=|<|>)(\d+):(\w+)$").unwrap();
+        }
 
-            match op.as_str() {
-                "<" => Ok(Op::LessThan {
-                    variable,
-                    value,
-                    output,
-                }),
-                ">" => Ok(Op::GreaterThan {
-                    variable,
+        if let Some(capture) = RULE.captures(s) {
+            let value = capture[3]
+                .parse()
+                .map_err(|e| format!("Invalid value {}: {e}", &capture[3]))?;
+            Ok(Rule {
+                condition: Some(Condition {
+                    attribute: capture[1].to_string(),
+                    comparison: capture[2].parse()?,
                     value,
-                    output,
                 }),
-                _ => panic!("Unknown operator {op}"),
-            }
+                target: capture[4].parse()?,
+            })
+        } else if s.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            Ok(Rule {
+                condition: None,
+                target: s.parse()?,
+            })
         } else {
-            let output = s.parse().unwrap();
-            Ok(Op::Identity { output })
+            Err(format!("Invalid rule {s}"))
         }
     }
 }
 
 struct Workflow {
-    rules: Vec<Op>,
+    rules: Vec<Rule>,
 }
 
-struct Workflows {
-    workflows: HashMap<Id, Workflow>,
+/// The ratings of a part, by attribute name.
+pub type Part = HashMap<String, u64>;
+
+/// A box of parts, with an inclusive range of values for each attribute.
+pub type Region = BTreeMap<String, Range>;
+
+const START: &str = "in";
+
+/// The attributes of the puzzle's parts, in the order they are listed.
+const XMAS: [&str; 4] = ["x", "m", "a", "s"];
+
+pub struct Workflows {
+    workflows: HashMap<String, Workflow>,
 }
 
 impl Workflows {
-    fn process(&self, input: &HashMap<String, usize>) -> &Id {
-        let mut workflow = self.workflows.get(&Id::In).unwrap();
-        while let Some(rule) = workflow.rules.iter().find(|rule| rule.matches(input)) {
-            let id = rule.output();
-            if id == &Id::R || id == &Id::A {
-                return id;
+    /// Checks that the start workflow and every workflow sent to exists, and that each workflow
+    /// ends with a rule that applies to every part.
+    fn validate(&self) -> Result<(), String> {
+        if !self.workflows.contains_key(START) {
+            return Err(format!("Missing workflow {START}"));
+        }
+        for (name, workflow) in &self.workflows {
+            match workflow.rules.last() {
+                None => return Err(format!("Workflow {name} has no rules")),
+                Some(rule) if rule.condition.is_some() => {
+                    return Err(format!("Workflow {name} does not end with a default rule"))
+                }
+                _ => {}
+            }
+            for rule in &workflow.rules {
+                if let Target::Workflow(target) = &rule.target {
+                    if !self.workflows.contains_key(target) {
+                        return Err(format!("Workflow {name} sends parts to unknown {target}"));
+                    }
+                }
             }
-            workflow = self.workflows.get(id).unwrap();
         }
+        Ok(())
+    }
 
-        panic!("Could not end");
-    }
-
-    /// We set the range of values to the maximum we expect as the input and for each rule we
-    /// either split the range in two to satisfy both branches of the rules and push the new
-    /// range that satisfies the rule to the stack and continue down the workflow with now
-    /// split range. The exception to this is the identity rule which just pushes to the stack
-    /// and we assume that is the last in the workflow.
-    fn process_range(
-        &self,
-        min_value: usize,
-        max_value: usize,
-    ) -> Vec<HashMap<&str, (usize, usize)>> {
-        let mut stack = vec![];
-        let mut input = HashMap::new();
-
-        input.insert("x", (min_value, max_value));
-        input.insert("m", (min_value, max_value));
-        input.insert("a", (min_value, max_value));
-        input.insert("s", (min_value, max_value));
-
-        stack.push((&Id::In, input));
-
-        let mut accepted_ranges = vec![];
-
-        while let Some((workflow_id, mut ranges)) = stack.pop() {
-            if workflow_id == &Id::A {
-                accepted_ranges.push(ranges);
-            } else if workflow_id != &Id::R {
-                let workflow = self.workflows.get(workflow_id).unwrap();
-
-                for rule in &workflow.rules {
-                    match rule {
-                        Op::LessThan {
-                            variable,
-                            value,
-                            output,
-                        } => {
-                            let (min, max) = ranges[variable.as_str()];
-
-                            if (min..=max).contains(value) {
-                                let mut new_ranges = ranges.clone();
-
-                                new_ranges.insert(variable, (min, value - 1));
-                                stack.push((output, new_ranges));
-
-                                ranges.insert(variable, (*value, max));
-                            }
-                        }
-                        Op::GreaterThan {
-                            variable,
-                            value,
-                            output,
-                        } => {
-                            let (min, max) = ranges[variable.as_str()];
-
-                            if (min..=max).contains(value) {
-                                let mut new_ranges = ranges.clone();
+    /// Whether the part is accepted. Workflows have no state, so a part that comes back to a
+    /// workflow it has already been through would loop forever and is an error.
+    pub fn process(&self, part: &Part) -> Result<bool, String> {
+        let mut path = vec![START];
+        loop {
+            let workflow = &self.workflows[*path.last().unwrap()];
+            let mut target = None;
+            for rule in &workflow.rules {
+                if rule
+                    .condition
+                    .as_ref()
+                    .map_or(Ok(true), |c| c.matches(part))?
+                {
+                    target = Some(&rule.target);
+                    break;
+                }
+            }
 
-                                new_ranges.insert(variable, (value + 1, max));
-                                stack.push((output, new_ranges));
+            match target {
+                Some(Target::Accept) => return Ok(true),
+                Some(Target::Reject) => return Ok(false),
+                Some(Target::Workflow(name)) => {
+                    if path.contains(&name.as_str()) {
+                        path.push(name);
+                        return Err(format!("Workflows loop: {}", path.join(" -> ")));
+                    }
+                    path.push(name);
+                }
+                None => unreachable!("Validated workflows end with a default rule"),
+            }
+        }
+    }
 
-                                ranges.insert(variable, (min, *value));
+    /// Splits the region into the boxes of parts that are accepted. Each rule cuts the boxes
+    /// reaching it into the parts that match, which go on to the rule's target, and the rest,
+    /// which go on to the next rule.
+    pub fn accepted(&self, region: Region) -> Result<Vec<Region>, String> {
+        let mut stack = vec![(vec![START], region)];
+        let mut accepted = Vec::new();
+
+        while let Some((path, region)) = stack.pop() {
+            let workflow = &self.workflows[*path.last().unwrap()];
+            let mut remaining = vec![region];
+
+            for rule in &workflow.rules {
+                let mut matching = Vec::new();
+                match &rule.condition {
+                    None => matching = std::mem::take(&mut remaining),
+                    Some(condition) => {
+                        let mut rest = Vec::new();
+                        for region in remaining {
+                            let range = *region.get(&condition.attribute).ok_or(format!(
+                                "Region has no attribute {}",
+                                condition.attribute
+                            ))?;
+                            let (yes, no) = condition.split(range);
+                            for (ranges, regions) in [(yes, &mut matching), (no, &mut rest)] {
+                                for range in ranges {
+                                    let mut region = region.clone();
+                                    region.insert(condition.attribute.clone(), range);
+                                    regions.push(region);
+                                }
                             }
                         }
-                        Op::Identity { output } => {
-                            stack.push((output, ranges.clone()));
+                        remaining = rest;
+                    }
+                }
+
+                match &rule.target {
+                    Target::Accept => accepted.extend(matching),
+                    Target::Reject => {}
+                    Target::Workflow(name) if !matching.is_empty() => {
+                        let mut path = path.clone();
+                        let looped = path.contains(&name.as_str());
+                        path.push(name);
+                        if looped {
+                            return Err(format!("Workflows loop: {}", path.join(" -> ")));
                         }
+                        stack.extend(matching.into_iter().map(|region| (path.clone(), region)));
                     }
+                    Target::Workflow(_) => {}
                 }
             }
         }
-        accepted_ranges
+        Ok(accepted)
     }
 }
 
 impl FromStr for Workflows {
-    type Err = ();
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let rules = s
-            .lines()
-            .map(|line| {
-                let (id, rest) = line.split_once('{').unwrap();
-                let rest = rest.trim_end_matches('}');
-                let rules = rest
-                    .split(',')
-                    .map(|s| Op::from_str(s).unwrap())
-                    .collect::<Vec<_>>();
-
-                (id.parse().unwrap(), Workflow { rules })
+        let mut workflows = HashMap::new();
+        for line in s.lines() {
+            let (name, rest) = line
+                .split_once('{')
+                .ok_or(format!("Invalid workflow {line}"))?;
+            let rules = rest
+                .strip_suffix('}')
+                .ok_or(format!("Invalid workflow {line}"))?
+                .split(',')
+                .map(|rule| rule.parse())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if workflows
+                .insert(name.to_string(), Workflow { rules })
+                .is_some()
+            {
+                return Err(format!("Workflow {name} is defined twice"));
+            }
+        }
+
+        let workflows = Workflows { workflows };
+        workflows.validate()?;
+        Ok(workflows)
+    }
+}
+
+pub fn parse_part(s: &str) -> Result<Part, String> {
+    s.strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or(format!("Invalid part {s}"))?
+        .split(',')
+        .map(|rating| {
+            let (name, value) = rating
+                .split_once('=')
+                .ok_or(format!("Invalid rating {rating}"))?;
+            let value = value
+                .parse()
+                .map_err(|e| format!("Invalid rating {rating}: {e}"))?;
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Parses a puzzle part, listing `XMAS` in order, straight into its ratings.
+fn parse_xmas(s: &str) -> Result<[u64; 4], String> {
+    let mut values = s
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or(format!("Invalid part {s}"))?
+        .split(',');
+    let mut ratings = [0; 4];
+    for (rating, attribute) in ratings.iter_mut().zip(XMAS) {
+        *rating = values
+            .next()
+            .and_then(|value| value.strip_prefix(attribute)?.strip_prefix('='))
+            .ok_or(format!("Expected {attribute} in part {s}"))?
+            .parse()
+            .map_err(|e| format!("Invalid part {s}: {e}"))?;
+    }
+    match values.next() {
+        Some(rating) => Err(format!("Unexpected rating {rating} in part {s}")),
+        None => Ok(ratings),
+    }
+}
+
+/// Where a node of the decision tree sends a part: one of the two leaves, or another node.
+type NodeId = usize;
+
+const REJECT: NodeId = 0;
+const ACCEPT: NodeId = 1;
+
+/// A rule with a condition, compiled so that the attribute is an index into the part.
+struct Node {
+    workflow: String,
+    condition: Condition,
+    attribute: usize,
+    then: NodeId,
+    then_target: Target,
+    /// The default-only rules passed through after the condition matches.
+    then_via: Via,
+    otherwise: NodeId,
+    /// The default rules passed through when it doesn't, starting with this workflow's own if
+    /// that is the next rule.
+    otherwise_via: Via,
+}
+
+/// The default rules, as workflow and target, followed on the way to a node without checking
+/// anything. They vanish from the graph when compiled, so are kept to explain a part.
+type Via = Vec<(String, Target)>;
+
+/// One rule a part was checked against on its way through the workflows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub workflow: String,
+    /// The condition checked, or `None` for a default rule.
+    pub condition: Option<Condition>,
+    pub matched: bool,
+    pub target: Target,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.condition, self.matched) {
+            (Some(condition), true) => {
+                write!(f, "{}: {condition} -> {}", self.workflow, self.target)
+            }
+            (Some(condition), false) => write!(f, "{}: not {condition}", self.workflow),
+            (None, _) => write!(f, "{}: default -> {}", self.workflow, self.target),
+        }
+    }
+}
+
+/// The workflows flattened into a graph of conditions, where parts are slices of ratings and
+/// evaluating one involves no hashing. Identical workflows reached from different places share
+/// their nodes, so the graph is the same size as the workflows.
+pub struct DecisionTree {
+    attributes: Vec<String>,
+    /// The two leaves are at `REJECT` and `ACCEPT` and have no node.
+    nodes: Vec<Option<Node>>,
+    root: NodeId,
+    root_via: Via,
+}
+
+impl DecisionTree {
+    /// Compiles the workflows starting from `in`. Unlike `Workflows::process`, any loop between
+    /// workflows is an error, even one that no part could follow.
+    pub fn compile(workflows: &Workflows) -> Result<Self, String> {
+        let mut attributes = workflows
+            .workflows
+            .values()
+            .flat_map(|workflow| &workflow.rules)
+            .filter_map(|rule| rule.condition.as_ref())
+            .map(|condition| condition.attribute.as_str())
+            .collect::<Vec<_>>();
+        attributes.sort();
+        attributes.dedup();
+        DecisionTree::compile_for(workflows, &attributes)
+    }
+
+    /// Compiles the workflows for ratings listed in the order of `attributes`, which must
+    /// include every attribute the workflows check.
+    pub fn compile_for(workflows: &Workflows, attributes: &[&str]) -> Result<Self, String> {
+        for rule in workflows.workflows.values().flat_map(|w| &w.rules) {
+            if let Some(condition) = &rule.condition {
+                if !attributes.contains(&condition.attribute.as_str()) {
+                    return Err(format!("Unknown attribute {}", condition.attribute));
+                }
+            }
+        }
+
+        let mut tree = DecisionTree {
+            attributes: attributes.iter().map(|a| a.to_string()).collect(),
+            nodes: vec![None, None],
+            root: REJECT,
+            root_via: Vec::new(),
+        };
+        let mut compiled = HashMap::new();
+        let mut path = Vec::new();
+        (tree.root, tree.root_via) = tree.compile_target(
+            workflows,
+            &Target::Workflow(START.to_string()),
+            &mut compiled,
+            &mut path,
+        )?;
+        Ok(tree)
+    }
+
+    fn compile_target<'a>(
+        &mut self,
+        workflows: &'a Workflows,
+        target: &'a Target,
+        compiled: &mut HashMap<(&'a str, usize), (NodeId, Via)>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(NodeId, Via), String> {
+        match target {
+            Target::Accept => Ok((ACCEPT, Vec::new())),
+            Target::Reject => Ok((REJECT, Vec::new())),
+            Target::Workflow(name) => {
+                if let Some(compiled) = compiled.get(&(name.as_str(), 0)) {
+                    return Ok(compiled.clone());
+                }
+                let looped = path.contains(&name.as_str());
+                path.push(name);
+                if looped {
+                    return Err(format!("Workflows loop: {}", path.join(" -> ")));
+                }
+                let compiled = self.compile_rule(workflows, name, 0, compiled, path)?;
+                path.pop();
+                Ok(compiled)
+            }
+        }
+    }
+
+    fn compile_rule<'a>(
+        &mut self,
+        workflows: &'a Workflows,
+        name: &'a str,
+        index: usize,
+        compiled: &mut HashMap<(&'a str, usize), (NodeId, Via)>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(NodeId, Via), String> {
+        if let Some(compiled) = compiled.get(&(name, index)) {
+            return Ok(compiled.clone());
+        }
+
+        let rule = &workflows.workflows[name].rules[index];
+        let (then, then_via) = self.compile_target(workflows, &rule.target, compiled, path)?;
+        let result = match &rule.condition {
+            None => {
+                let mut via = vec![(name.to_string(), rule.target.clone())];
+                via.extend(then_via);
+                (then, via)
+            }
+            Some(condition) => {
+                let (otherwise, otherwise_via) =
+                    self.compile_rule(workflows, name, index + 1, compiled, path)?;
+                self.nodes.push(Some(Node {
+                    workflow: name.to_string(),
+                    condition: condition.clone(),
+                    attribute: self
+                        .attributes
+                        .iter()
+                        .position(|attribute| attribute == &condition.attribute)
+                        .unwrap(),
+                    then,
+                    then_target: rule.target.clone(),
+                    then_via,
+                    otherwise,
+                    otherwise_via,
+                }));
+                (self.nodes.len() - 1, Vec::new())
+            }
+        };
+        compiled.insert((name, index), result.clone());
+        Ok(result)
+    }
+
+    /// The part's ratings in the order of `attributes`.
+    pub fn ratings(&self, part: &Part) -> Result<Vec<u64>, String> {
+        self.attributes
+            .iter()
+            .map(|attribute| {
+                part.get(attribute)
+                    .copied()
+                    .ok_or(format!("Part has no attribute {attribute}"))
             })
-            .collect::<HashMap<_, _>>();
-        Ok(Workflows { workflows: rules })
+            .collect()
+    }
+
+    pub fn evaluate(&self, ratings: &[u64]) -> bool {
+        let mut id = self.root;
+        while let Some(node) = &self.nodes[id] {
+            id = if node
+                .condition
+                .comparison
+                .holds(ratings[node.attribute], node.condition.value)
+            {
+                node.then
+            } else {
+                node.otherwise
+            };
+        }
+        id == ACCEPT
+    }
+
+    /// Every rule the part is checked against, in order, and whether it is accepted.
+    pub fn explain(&self, ratings: &[u64]) -> (Vec<Step>, bool) {
+        let mut steps = Vec::new();
+        let defaults = |steps: &mut Vec<Step>, via: &Via| {
+            steps.extend(via.iter().map(|(workflow, target)| Step {
+                workflow: workflow.clone(),
+                condition: None,
+                matched: true,
+                target: target.clone(),
+            }));
+        };
+        let mut id = self.root;
+        defaults(&mut steps, &self.root_via);
+
+        while let Some(node) = &self.nodes[id] {
+            let matched = node
+                .condition
+                .comparison
+                .holds(ratings[node.attribute], node.condition.value);
+            steps.push(Step {
+                workflow: node.workflow.clone(),
+                condition: Some(node.condition.clone()),
+                matched,
+                target: node.then_target.clone(),
+            });
+            if matched {
+                defaults(&mut steps, &node.then_via);
+                id = node.then;
+            } else {
+                defaults(&mut steps, &node.otherwise_via);
+                id = node.otherwise;
+            }
+        }
+        (steps, id == ACCEPT)
+    }
+
+    /// The conditions along every path to acceptance. A part is accepted exactly when it meets
+    /// every condition of one of the paths, and no part meets more than one.
+    pub fn accepting_paths(&self) -> Vec<Vec<Condition>> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(self.root, Vec::new())];
+        while let Some((id, conditions)) = stack.pop() {
+            match &self.nodes[id] {
+                None if id == ACCEPT => paths.push(conditions),
+                None => {}
+                Some(node) => {
+                    let mut otherwise = conditions.clone();
+                    otherwise.push(node.condition.negate());
+                    stack.push((node.otherwise, otherwise));
+
+                    let mut then = conditions;
+                    then.push(node.condition.clone());
+                    stack.push((node.then, then));
+                }
+            }
+        }
+        paths
     }
 }
 
+/// Describes how each part is decided, followed by the conditions of every accepting path.
+pub fn explain(input: &str) -> Result<String, String> {
+    let (rules, parts) = input
+        .split_once("\n\n")
+        .ok_or("Missing blank line between workflows and parts")?;
+    let tree = DecisionTree::compile(&rules.parse()?)?;
+
+    let mut output = String::new();
+    for line in parts.lines() {
+        let (steps, accepted) = tree.explain(&tree.ratings(&parse_part(line)?)?);
+        output.push_str(&format!(
+            "{line}: {}\n",
+            if accepted { "accepted" } else { "rejected" }
+        ));
+        for step in steps {
+            output.push_str(&format!("  {step}\n"));
+        }
+    }
+
+    output.push_str("Accepting paths:\n");
+    for path in tree.accepting_paths() {
+        let conditions = path.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        output.push_str(&format!("  {}\n", conditions.join(" and ")));
+    }
+    Ok(output)
+}
+
 pub fn star_one(mut input: impl BufRead) -> String {
     let mut str = String::new();
     input.read_to_string(&mut str).unwrap();
@@ -231,21 +700,14 @@ pub fn star_one(mut input: impl BufRead) -> String {
     let (rules, parts) = str.split_once("\n\n").unwrap();
 
     let workflows: Workflows = rules.parse().unwrap();
+    let tree = DecisionTree::compile_for(&workflows, &XMAS).unwrap();
 
     parts
         .lines()
-        .map(|part| {
-            part.trim_matches(|c| c == '{' || c == '}')
-                .split(',')
-                .map(|part| {
-                    let (id, value) = part.split_once('=').unwrap();
-                    (id.to_string(), value.parse::<usize>().unwrap())
-                })
-                .collect::<HashMap<_, _>>()
-        })
-        .filter(|part| workflows.process(part) == &Id::A)
-        .map(|part| part.values().sum::<usize>())
-        .sum::<usize>()
+        .map(|part| parse_xmas(part).unwrap())
+        .filter(|ratings| tree.evaluate(ratings))
+        .map(|ratings| ratings.iter().sum::<u64>())
+        .sum::<u64>()
         .to_string()
 }
 
@@ -257,23 +719,29 @@ pub fn star_two(mut input: impl BufRead) -> String {
 
     let workflows: Workflows = rules.parse().unwrap();
 
-    let accepted_ranges = workflows.process_range(1, 4000);
-
-    accepted_ranges
-        .into_par_iter()
-        .map(|part| {
-            part.values()
-                .map(|(min, max)| *min..=*max)
-                .multi_cartesian_product()
-                .count()
+    let region = XMAS
+        .into_iter()
+        .map(|attribute| (attribute.to_string(), (1, 4000)))
+        .collect();
+
+    workflows
+        .accepted(region)
+        .unwrap()
+        .iter()
+        .map(|region| {
+            region
+                .values()
+                .map(|(min, max)| max - min + 1)
+                .product::<u64>()
         })
-        .sum::<usize>()
+        .sum::<u64>()
         .to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::Lcg;
     use std::io::Cursor;
 
     #[test]
@@ -327,4 +795,210 @@ hdj{m>838:A,pv}
             "167409079868000"
         );
     }
+
+    #[test]
+    fn test_operators_and_attribute_names() {
+        let workflows = "in{weight>=10:heavy,colour==3:A,colour!=7:R,A}
+heavy{weight<=20:A,R}"
+            .parse::<Workflows>()
+            .unwrap();
+        let part = |weight, colour| {
+            Part::from([
+                ("weight".to_string(), weight),
+                ("colour".to_string(), colour),
+            ])
+        };
+
+        assert_eq!(workflows.process(&part(10, 0)), Ok(true));
+        assert_eq!(workflows.process(&part(21, 3)), Ok(false));
+        assert_eq!(workflows.process(&part(9, 3)), Ok(true));
+        assert_eq!(workflows.process(&part(9, 7)), Ok(true));
+        assert_eq!(workflows.process(&part(9, 8)), Ok(false));
+        assert!(workflows.process(&Part::new()).is_err());
+
+        // Check the regions against every part in a small space.
+        let region = Region::from([
+            ("weight".to_string(), (0, 30)),
+            ("colour".to_string(), (0, 9)),
+        ]);
+        let accepted = workflows.accepted(region).unwrap();
+        for weight in 0..=30 {
+            for colour in 0..=9 {
+                let count = accepted
+                    .iter()
+                    .filter(|region| {
+                        let (w, c) = (region["weight"], region["colour"]);
+                        (w.0..=w.1).contains(&weight) && (c.0..=c.1).contains(&colour)
+                    })
+                    .count();
+                let expected = workflows.process(&part(weight, colour)).unwrap();
+                assert_eq!(count, expected as usize, "{weight} {colour}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!("px{a<5:A,R}".parse::<Workflows>().is_err());
+        assert!("in{a<5:px,R}".parse::<Workflows>().is_err());
+        assert!("in{a<5:A,x>2:R}".parse::<Workflows>().is_err());
+        assert!("in{a<5:A,R}\nin{R}".parse::<Workflows>().is_err());
+        assert!("in{a<>5:A,R}".parse::<Workflows>().is_err());
+        assert!("in{a<5:A,R}".parse::<Workflows>().is_ok());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let workflows = "in{a<5:px,A}
+px{a<3:in,R}"
+            .parse::<Workflows>()
+            .unwrap();
+
+        let part = |a| Part::from([("a".to_string(), a)]);
+        assert_eq!(workflows.process(&part(4)), Ok(false));
+        assert_eq!(
+            workflows.process(&part(2)),
+            Err("Workflows loop: in -> px -> in".to_string())
+        );
+        assert!(workflows
+            .accepted(Region::from([("a".to_string(), (1, 10))]))
+            .is_err());
+        assert!(workflows
+            .accepted(Region::from([("a".to_string(), (3, 10))]))
+            .is_ok());
+    }
+
+    const WORKFLOWS: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}";
+
+    #[test]
+    fn test_decision_tree_matches_workflows() {
+        let workflows = WORKFLOWS.parse::<Workflows>().unwrap();
+        let tree = DecisionTree::compile(&workflows).unwrap();
+        assert_eq!(tree.attributes, ["a", "m", "s", "x"]);
+
+        let mut lcg = Lcg::new(19);
+        let mut random = || 1 + lcg.next_u64() % 4000;
+        for _ in 0..1000 {
+            let part = ["x", "m", "a", "s"]
+                .into_iter()
+                .map(|attribute| (attribute.to_string(), random()))
+                .collect::<Part>();
+            let ratings = tree.ratings(&part).unwrap();
+            let accepted = workflows.process(&part).unwrap();
+            assert_eq!(tree.evaluate(&ratings), accepted);
+            assert_eq!(tree.explain(&ratings).1, accepted);
+        }
+    }
+
+    #[test]
+    fn test_xmas_ratings() {
+        assert_eq!(
+            parse_xmas("{x=787,m=2655,a=1222,s=2876}"),
+            Ok([787, 2655, 1222, 2876])
+        );
+        assert!(parse_xmas("{m=2655,x=787,a=1222,s=2876}").is_err());
+        assert!(parse_xmas("{x=787,m=2655,a=1222}").is_err());
+        assert!(parse_xmas("{x=787,m=2655,a=1222,s=2876,q=1}").is_err());
+
+        let workflows = WORKFLOWS.parse::<Workflows>().unwrap();
+        let tree = DecisionTree::compile_for(&workflows, &XMAS).unwrap();
+        assert!(tree.evaluate(&[787, 2655, 1222, 2876]));
+        assert!(!tree.evaluate(&[1679, 44, 2067, 496]));
+        assert!(DecisionTree::compile_for(&workflows, &["x", "m", "a"]).is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let workflows = WORKFLOWS.parse::<Workflows>().unwrap();
+        let tree = DecisionTree::compile(&workflows).unwrap();
+
+        let part = parse_part("{x=787,m=2655,a=1222,s=2876}").unwrap();
+        let (steps, accepted) = tree.explain(&tree.ratings(&part).unwrap());
+        assert!(accepted);
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "in: not s<1351",
+                "in: default -> qqz",
+                "qqz: s>2770 -> qs",
+                "qs: not s>3448",
+                "qs: default -> lnx",
+                "lnx: m>1548 -> A",
+            ]
+        );
+
+        let tree = DecisionTree::compile(&"in{R}".parse().unwrap()).unwrap();
+        let (steps, accepted) = tree.explain(&[]);
+        assert!(!accepted);
+        assert_eq!(steps[0].to_string(), "in: default -> R");
+
+        // Workflows with only a default rule leave no node behind but still show up.
+        let tree = DecisionTree::compile(&"in{a<5:w,A}\nw{R}".parse().unwrap()).unwrap();
+        let part = parse_part("{a=1}").unwrap();
+        let (steps, accepted) = tree.explain(&tree.ratings(&part).unwrap());
+        assert!(!accepted);
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>(),
+            ["in: a<5 -> w", "w: default -> R"]
+        );
+    }
+
+    #[test]
+    fn test_accepting_paths() {
+        let workflows = WORKFLOWS.parse::<Workflows>().unwrap();
+        let tree = DecisionTree::compile(&workflows).unwrap();
+        let paths = tree.accepting_paths();
+        assert!(paths[0].iter().any(|c| c.to_string() == "s<1351"));
+
+        // Intersecting each path's conditions gives disjoint boxes covering the accepted parts.
+        let total = paths
+            .iter()
+            .map(|path| {
+                let mut region = ["x", "m", "a", "s"]
+                    .into_iter()
+                    .map(|attribute| (attribute.to_string(), vec![(1, 4000)]))
+                    .collect::<BTreeMap<_, _>>();
+                for condition in path {
+                    let ranges = region.get_mut(&condition.attribute).unwrap();
+                    *ranges = ranges
+                        .iter()
+                        .flat_map(|&range| condition.split(range).0)
+                        .collect();
+                }
+                region
+                    .values()
+                    .map(|ranges| ranges.iter().map(|(min, max)| max - min + 1).sum::<u64>())
+                    .product::<u64>()
+            })
+            .sum::<u64>();
+        assert_eq!(total, 167409079868000);
+    }
+
+    #[test]
+    fn test_compile_loops() {
+        let workflows = "in{a<5:px,A}
+px{a<3:in,R}"
+            .parse::<Workflows>()
+            .unwrap();
+        assert_eq!(
+            DecisionTree::compile(&workflows).err(),
+            Some("Workflows loop: in -> px -> in".to_string())
+        );
+    }
 }
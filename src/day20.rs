@@ -1,10 +1,12 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     io::BufRead,
 };
 
 use num_integer::Integer;
 
+use crate::cycle;
+
 #[derive(Debug)]
 enum Module {
     Broadcast,
@@ -56,13 +58,25 @@ impl<'a> Circuit<'a> {
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Hash)]
 struct CircuitState<'a> {
-    flipflops: HashMap<&'a str, bool>,
-    conjunctions: HashMap<&'a str, HashMap<&'a str, bool>>,
+    flipflops: BTreeMap<&'a str, bool>,
+    conjunctions: BTreeMap<&'a str, BTreeMap<&'a str, bool>>,
 }
 
 impl<'a> CircuitState<'a> {
     fn construct(circuit: &'a Circuit) -> Self {
+        // Every flip-flop starts off, so the first state compares equal to any later one with
+        // the same values.
+        let flipflops = circuit
+            .modules
+            .iter()
+            .filter_map(|(name, module)| match module {
+                Module::FlipFlop => Some((*name, false)),
+                _ => None,
+            })
+            .collect();
+
         let conjunction_inputs = circuit
             .modules
             .iter()
@@ -79,101 +93,121 @@ impl<'a> CircuitState<'a> {
                     .map(move |from| (to, from))
             })
             .fold(
-                HashMap::new(),
-                |mut acc: HashMap<&str, HashMap<&str, bool>>, (to, from)| {
+                BTreeMap::new(),
+                |mut acc: BTreeMap<&str, BTreeMap<&str, bool>>, (to, from)| {
                     acc.entry(to).or_default().insert(from, false);
                     acc
                 },
             );
 
         Self {
-            flipflops: HashMap::new(),
+            flipflops,
             conjunctions: conjunction_inputs,
         }
     }
 }
 
-pub fn star_one(mut input: impl BufRead) -> String {
-    let mut str = String::new();
-    input.read_to_string(&mut str).unwrap();
-
-    let circuit = Circuit::from_str(&str).unwrap();
-
-    let mut circuit_state = CircuitState::construct(&circuit);
+/// Presses the button once, returning the number of low and high pulses sent.
+fn press_button<'a>(circuit: &Circuit<'a>, circuit_state: &mut CircuitState<'a>) -> (usize, usize) {
     let mut low_count = 0;
     let mut high_count = 0;
 
     let mut queue = VecDeque::new();
+    queue.push_back(("button", "broadcaster", false));
 
-    for _i in 0..1000 {
-        queue.push_back(("button", "broadcaster", false));
+    while let Some(pulse) = queue.pop_front() {
+        let (from_node, node, pulse) = pulse;
 
-        while let Some(pulse) = queue.pop_front() {
-            let (from_node, node, pulse) = pulse;
+        // println!("{} -> {} ({})", from_node, node, pulse);
 
-            // println!("{} -> {} ({})", from_node, node, pulse);
+        if pulse {
+            high_count += 1;
+        } else {
+            low_count += 1;
+        }
 
-            if pulse {
-                high_count += 1;
-            } else {
-                low_count += 1;
-            }
+        if !circuit.modules.contains_key(node) {
+            // println!("Unable to find {}", node);
+            continue;
+        }
 
-            if !circuit.modules.contains_key(node) {
-                // println!("Unable to find {}", node);
-                continue;
+        match circuit
+            .modules
+            .get(node)
+            .unwrap_or_else(|| panic!("Unable to find {node}"))
+        {
+            Module::Broadcast => {
+                let outputs = circuit.outputs.get(node).unwrap();
+                for output in outputs {
+                    queue.push_back((node, output, pulse));
+                }
             }
+            Module::FlipFlop => {
+                let outputs = circuit.outputs.get(node).unwrap();
+                let is_on = circuit_state.flipflops.get_mut(node).unwrap();
 
-            match circuit
-                .modules
-                .get(node)
-                .unwrap_or_else(|| panic!("Unable to find {node}"))
-            {
-                Module::Broadcast => {
-                    let outputs = circuit.outputs.get(node).unwrap();
-                    for output in outputs {
-                        queue.push_back((node, output, pulse));
-                    }
+                if pulse {
+                    // We received a high pulse so don't send anything
+                    continue;
                 }
-                Module::FlipFlop => {
-                    let outputs = circuit.outputs.get(node).unwrap();
-                    let is_on = circuit_state.flipflops.entry(node).or_insert(false);
-
-                    if pulse {
-                        // We received a high pulse so don't send anything
-                        continue;
-                    }
 
-                    let pulse_to_send = if *is_on {
-                        *is_on = false;
-                        false
-                    } else {
-                        *is_on = true;
-                        true
-                    };
-                    for output in outputs {
-                        queue.push_back((node, output, pulse_to_send));
-                    }
+                let pulse_to_send = if *is_on {
+                    *is_on = false;
+                    false
+                } else {
+                    *is_on = true;
+                    true
+                };
+                for output in outputs {
+                    queue.push_back((node, output, pulse_to_send));
                 }
-                Module::Conjunction => {
-                    let outputs = circuit.outputs.get(node).unwrap();
-                    let conjunction = circuit_state.conjunctions.entry(node).or_default();
+            }
+            Module::Conjunction => {
+                let outputs = circuit.outputs.get(node).unwrap();
+                let conjunction = circuit_state.conjunctions.entry(node).or_default();
 
-                    let previous_pulse: &mut bool = conjunction
-                        .get_mut(from_node)
-                        .unwrap_or_else(|| panic!("Unable to find {from_node} in"));
-                    *previous_pulse = pulse;
+                let previous_pulse: &mut bool = conjunction
+                    .get_mut(from_node)
+                    .unwrap_or_else(|| panic!("Unable to find {from_node} in"));
+                *previous_pulse = pulse;
 
-                    let pulse_to_send = !conjunction.values().all(|pulse| *pulse);
+                let pulse_to_send = !conjunction.values().all(|pulse| *pulse);
 
-                    for output in outputs {
-                        queue.push_back((node, output, pulse_to_send));
-                    }
+                for output in outputs {
+                    queue.push_back((node, output, pulse_to_send));
                 }
             }
         }
     }
 
+    (low_count, high_count)
+}
+
+pub fn star_one(mut input: impl BufRead) -> String {
+    let mut str = String::new();
+    input.read_to_string(&mut str).unwrap();
+
+    let circuit = Circuit::from_str(&str).unwrap();
+
+    let presses = 1000;
+
+    // The pulses sent by each press only depend on the state before it, so once the circuit
+    // returns to an earlier state the counts repeat too.
+    let mut counts = Vec::new();
+    let (_, cycle) = cycle::hashed(
+        CircuitState::construct(&circuit),
+        |circuit_state| {
+            let mut circuit_state = circuit_state.clone();
+            counts.push(press_button(&circuit, &mut circuit_state));
+            circuit_state
+        },
+        presses,
+    );
+
+    let (low_count, high_count) = (0..presses)
+        .map(|press| counts[cycle.map_or(press, |cycle| cycle.earliest_equivalent(press))])
+        .fold((0, 0), |(low, high), (l, h)| (low + l, high + h));
+
     (high_count * low_count).to_string()
 }
 
@@ -234,14 +268,15 @@ pub fn star_two(mut input: impl BufRead) -> String {
                     }
                 }
                 Some(Module::FlipFlop) => {
-                    // We received a low pulse so process the pulse
-                    if !is_high {
-                        let is_on = circuit_state.flipflops.entry(node).or_insert(false);
-                        *is_on = !*is_on;
-
-                        for output in circuit.outputs.get(node).unwrap() {
-                            queue.push_back((node, output, *is_on));
-                        }
+                    // Only a low pulse is processed
+                    if is_high {
+                        continue;
+                    }
+                    let is_on = circuit_state.flipflops.get_mut(node).unwrap();
+                    *is_on = !*is_on;
+
+                    for output in circuit.outputs.get(node).unwrap() {
+                        queue.push_back((node, output, *is_on));
                     }
                 }
                 Some(Module::Conjunction) => {
@@ -292,6 +327,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_returns_to_start() {
+        let input = "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output";
+        let circuit = Circuit::from_str(input).unwrap();
+        let start = CircuitState::construct(&circuit);
+        let mut circuit_state = start.clone();
+        for press in 1..=4 {
+            press_button(&circuit, &mut circuit_state);
+            assert_eq!(circuit_state == start, press == 4);
+        }
+    }
+
     // #[test]
     // fn test_star_two() {
     //     assert_eq!(star_two(Cursor::new(b"")), "167409079868000");
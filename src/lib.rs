@@ -8,26 +8,32 @@ mod day01;
 mod day02;
 mod day03;
 mod day04;
-mod day05;
+pub mod day05;
 mod day06;
-mod day07;
+pub mod day07;
 mod day08;
-mod day09;
-mod day10;
-mod day11;
+pub mod day09;
+pub mod day10;
+pub mod day11;
 mod day12;
 mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
 mod day20;
 mod day21;
 mod day23;
 mod day24;
 
+pub mod cycle;
+pub mod nonogram;
+pub mod polygon;
+#[cfg(test)]
+mod test_util;
+
 type DayFn = fn(Box<dyn BufRead>) -> String;
 
 pub fn get_day(day: usize) -> (DayFn, DayFn, PathBuf) {
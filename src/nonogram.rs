@@ -0,0 +1,232 @@
+//! Nonogram solver built on the run-length counting from day 12.
+//!
+//! Each line is solved by asking the day 12 counter how many ways the line can be completed with
+//! a cell filled and with it empty. If one choice has no arrangements the other is forced. When
+//! propagation stalls we guess a cell and backtrack.
+
+use crate::day12::{count_arrangements, Status};
+
+/// A solved puzzle where `true` is a filled cell.
+pub type Solution = Vec<Vec<bool>>;
+
+#[derive(Debug, Clone)]
+pub struct Nonogram {
+    rows: Vec<Vec<usize>>,
+    columns: Vec<Vec<usize>>,
+}
+
+type Grid = Vec<Vec<Status>>;
+
+impl Nonogram {
+    pub fn new(rows: Vec<Vec<usize>>, columns: Vec<Vec<usize>>) -> Self {
+        Nonogram { rows, columns }
+    }
+
+    fn line(&self, grid: &Grid, index: usize) -> (Vec<Status>, &[usize]) {
+        if index < self.rows.len() {
+            (grid[index].clone(), &self.rows[index])
+        } else {
+            let x = index - self.rows.len();
+            (
+                grid.iter().map(|row| row[x].clone()).collect(),
+                &self.columns[x],
+            )
+        }
+    }
+
+    fn set(&self, grid: &mut Grid, index: usize, i: usize, status: Status) {
+        if index < self.rows.len() {
+            grid[index][i] = status;
+        } else {
+            grid[i][index - self.rows.len()] = status;
+        }
+    }
+
+    /// Fills in every cell that is forced by a single row or column until nothing changes.
+    /// Returns false if a line can no longer be satisfied.
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        let line_count = self.rows.len() + self.columns.len();
+        let mut dirty = vec![true; line_count];
+
+        while let Some(index) = dirty.iter().position(|&d| d) {
+            dirty[index] = false;
+            let (mut line, runs) = self.line(grid, index);
+
+            if count_arrangements::<u128>(&line, runs) == 0 {
+                return false;
+            }
+
+            for i in 0..line.len() {
+                if line[i] != Status::Unknown {
+                    continue;
+                }
+
+                line[i] = Status::Damaged;
+                let filled = count_arrangements::<u128>(&line, runs);
+                line[i] = Status::Functional;
+                let empty = count_arrangements::<u128>(&line, runs);
+
+                let status = match (filled, empty) {
+                    (0, 0) => return false,
+                    (0, _) => Status::Functional,
+                    (_, 0) => Status::Damaged,
+                    _ => {
+                        line[i] = Status::Unknown;
+                        continue;
+                    }
+                };
+                line[i] = status.clone();
+                self.set(grid, index, i, status);
+
+                // The crossing line has a new cell so needs checking again.
+                dirty[if index < self.rows.len() {
+                    self.rows.len() + i
+                } else {
+                    i
+                }] = true;
+            }
+        }
+        true
+    }
+
+    fn search(&self, mut grid: Grid, limit: usize, solutions: &mut Vec<Solution>) {
+        if solutions.len() >= limit || !self.propagate(&mut grid) {
+            return;
+        }
+
+        let unknown = grid.iter().enumerate().find_map(|(y, row)| {
+            row.iter()
+                .position(|status| status == &Status::Unknown)
+                .map(|x| (y, x))
+        });
+
+        match unknown {
+            Some((y, x)) => {
+                for guess in [Status::Damaged, Status::Functional] {
+                    let mut grid = grid.clone();
+                    grid[y][x] = guess;
+                    self.search(grid, limit, solutions);
+                }
+            }
+            None => solutions.push(
+                grid.iter()
+                    .map(|row| row.iter().map(|s| s == &Status::Damaged).collect())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Finds up to `limit` solutions.
+    pub fn solve_limit(&self, limit: usize) -> Vec<Solution> {
+        let grid = vec![vec![Status::Unknown; self.columns.len()]; self.rows.len()];
+        let mut solutions = Vec::new();
+        self.search(grid, limit, &mut solutions);
+        solutions
+    }
+
+    /// Finds every solution.
+    pub fn solve(&self) -> Vec<Solution> {
+        self.solve_limit(usize::MAX)
+    }
+
+    /// Returns the solution if there is exactly one.
+    pub fn unique_solution(&self) -> Option<Solution> {
+        let mut solutions = self.solve_limit(2);
+        if solutions.len() == 1 {
+            solutions.pop()
+        } else {
+            None
+        }
+    }
+}
+
+pub fn render(solution: &Solution) -> String {
+    solution
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&filled| if filled { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(solution: &Solution) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let line_runs = |line: Vec<bool>| {
+            line.split(|&filled| !filled)
+                .filter(|run| !run.is_empty())
+                .map(|run| run.len())
+                .collect::<Vec<_>>()
+        };
+        let rows = solution.iter().map(|row| line_runs(row.clone())).collect();
+        let columns = (0..solution[0].len())
+            .map(|x| line_runs(solution.iter().map(|row| row[x]).collect()))
+            .collect();
+        (rows, columns)
+    }
+
+    #[test]
+    fn test_unique() {
+        let picture = "####.
+#....
+###..
+#....
+#....";
+        let solution = picture
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect())
+            .collect::<Solution>();
+        let (rows, columns) = runs(&solution);
+
+        let nonogram = Nonogram::new(rows, columns);
+        assert_eq!(nonogram.unique_solution(), Some(solution.clone()));
+        assert_eq!(render(&solution), picture);
+    }
+
+    #[test]
+    fn test_multiple_solutions() {
+        // A diagonal can go either way.
+        let nonogram = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        let solutions = nonogram.solve();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions.contains(&vec![vec![true, false], vec![false, true]]));
+        assert!(solutions.contains(&vec![vec![false, true], vec![true, false]]));
+        assert_eq!(nonogram.unique_solution(), None);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let nonogram = Nonogram::new(vec![vec![2], vec![]], vec![vec![1], vec![]]);
+        assert!(nonogram.solve().is_empty());
+    }
+
+    #[test]
+    fn test_needs_backtracking() {
+        let picture = "#.#..
+..#.#
+..###
+.#.#.
+##...";
+        let solution = picture
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect())
+            .collect::<Solution>();
+        let (rows, columns) = runs(&solution);
+        let nonogram = Nonogram::new(rows, columns);
+
+        // Propagation stalls with almost every cell unknown, so the solver has to guess.
+        let mut grid = vec![vec![Status::Unknown; 5]; 5];
+        assert!(nonogram.propagate(&mut grid));
+        assert!(grid
+            .iter()
+            .flatten()
+            .any(|status| status == &Status::Unknown));
+
+        assert_eq!(nonogram.solve(), vec![solution]);
+    }
+}
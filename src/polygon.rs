@@ -0,0 +1,308 @@
+//! Rectilinear polygons on the integer lattice, such as the lagoons dug out in day 18.
+//!
+//! A polygon is traced by a sequence of moves from the origin. Its area comes from the shoelace
+//! formula and Pick's theorem, `A = i + b/2 - 1`, relates that to the lattice points inside
+//! (`i`) and on (`b`) the boundary. Everything is exact `i128` arithmetic, which leaves plenty of
+//! room for dig plans with distances in the millions.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The unit step for the direction, with up as positive y.
+    fn delta(self) -> Point {
+        match self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+pub type Point = (i128, i128);
+
+/// Why a sequence of moves doesn't trace out a simple polygon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolygonError {
+    /// The moves finish somewhere other than where they started.
+    Unclosed { end: Point },
+    /// Edges `first` and `second` (indices into the moves) touch somewhere other than a shared
+    /// corner, at `at`.
+    SelfIntersection {
+        first: usize,
+        second: usize,
+        at: Point,
+    },
+    /// The moves enclose nothing.
+    Degenerate,
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolygonError::Unclosed { end } => {
+                write!(f, "Plan ends at {end:?} instead of returning to the start")
+            }
+            PolygonError::SelfIntersection { first, second, at } => {
+                write!(f, "Edge {first} crosses edge {second} at {at:?}")
+            }
+            PolygonError::Degenerate => write!(f, "Plan does not enclose any area"),
+        }
+    }
+}
+
+/// A simple rectilinear polygon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polygon {
+    /// The corners in order, starting at the origin. Each edge goes from a vertex to the next,
+    /// with the last edge returning to the first vertex.
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    /// Traces the moves from the origin, checking that they return to it without the boundary
+    /// touching itself. Moves of zero length are ignored.
+    pub fn from_moves(
+        moves: impl IntoIterator<Item = (Direction, u64)>,
+    ) -> Result<Self, PolygonError> {
+        let mut vertices = vec![(0, 0)];
+        let mut pos = (0, 0);
+        for (direction, distance) in moves {
+            if distance == 0 {
+                continue;
+            }
+            let (dx, dy) = direction.delta();
+            pos = (pos.0 + dx * distance as i128, pos.1 + dy * distance as i128);
+            vertices.push(pos);
+        }
+
+        if pos != (0, 0) {
+            return Err(PolygonError::Unclosed { end: pos });
+        }
+        vertices.pop();
+        if vertices.len() < 4 {
+            // Rectilinear polygons need at least four corners, anything less doubles back.
+            return Err(if vertices.is_empty() {
+                PolygonError::Degenerate
+            } else {
+                PolygonError::SelfIntersection {
+                    first: 0,
+                    second: 1,
+                    at: vertices[1],
+                }
+            });
+        }
+
+        let polygon = Polygon { vertices };
+        polygon.check_simple()?;
+        Ok(polygon)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    /// The edges as pairs of end points, in order.
+    pub fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(&a, &b)| (a, b))
+    }
+
+    /// Checks every pair of edges. Neighbouring edges may only share their corner, and any
+    /// other pair may not meet at all.
+    fn check_simple(&self) -> Result<(), PolygonError> {
+        let edges = self.edges().map(bounds).collect::<Vec<_>>();
+        let n = edges.len();
+        for first in 0..n {
+            // A neighbouring edge going straight back overlaps more than the shared corner.
+            let second = (first + 1) % n;
+            let (a, b) = (self.vertices[first], self.vertices[second]);
+            let c = self.vertices[(first + 2) % n];
+            let turn_back = (b.0 - a.0).signum() == -(c.0 - b.0).signum()
+                && (b.1 - a.1).signum() == -(c.1 - b.1).signum();
+            if turn_back {
+                return Err(PolygonError::SelfIntersection {
+                    first,
+                    second,
+                    at: b,
+                });
+            }
+
+            for second in first + 2..n {
+                if first == 0 && second == n - 1 {
+                    continue;
+                }
+                if let Some(at) = overlap(edges[first], edges[second]) {
+                    return Err(PolygonError::SelfIntersection { first, second, at });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Twice the signed area from the shoelace formula, positive when the vertices go
+    /// anticlockwise.
+    fn twice_signed_area(&self) -> i128 {
+        self.edges().map(|(a, b)| a.0 * b.1 - a.1 * b.0).sum()
+    }
+
+    /// The area enclosed by the boundary, running through the centres of the trench tiles.
+    pub fn area(&self) -> i128 {
+        self.twice_signed_area().abs() / 2
+    }
+
+    /// The number of lattice points on the boundary, which is its length for a rectilinear
+    /// polygon.
+    pub fn boundary_points(&self) -> i128 {
+        self.edges()
+            .map(|(a, b)| (b.0 - a.0).abs() + (b.1 - a.1).abs())
+            .sum()
+    }
+
+    /// The number of lattice points strictly inside, from Pick's theorem.
+    pub fn interior_points(&self) -> i128 {
+        (self.twice_signed_area().abs() - self.boundary_points()) / 2 + 1
+    }
+
+    /// The number of lattice points inside or on the boundary, which is the number of tiles dug
+    /// out when every point is the centre of a tile.
+    pub fn lattice_points(&self) -> i128 {
+        self.interior_points() + self.boundary_points()
+    }
+}
+
+/// The `(min x, max x, min y, max y)` of an edge.
+fn bounds((a, b): (Point, Point)) -> (i128, i128, i128, i128) {
+    (a.0.min(b.0), a.0.max(b.0), a.1.min(b.1), a.1.max(b.1))
+}
+
+/// A point shared by two axis aligned edges, if any. Their bounding boxes are the edges
+/// themselves, so they meet exactly when the boxes overlap.
+fn overlap(
+    (ax0, ax1, ay0, ay1): (i128, i128, i128, i128),
+    (bx0, bx1, by0, by1): (i128, i128, i128, i128),
+) -> Option<Point> {
+    let (x0, x1) = (ax0.max(bx0), ax1.min(bx1));
+    let (y0, y1) = (ay0.max(by0), ay1.min(by1));
+    (x0 <= x1 && y0 <= y1).then_some((x0, y0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::*;
+
+    #[test]
+    fn test_square() {
+        let polygon = Polygon::from_moves([(Right, 2), (Down, 2), (Left, 2), (Up, 2)]).unwrap();
+        assert_eq!(polygon.area(), 4);
+        assert_eq!(polygon.boundary_points(), 8);
+        assert_eq!(polygon.interior_points(), 1);
+        assert_eq!(polygon.lattice_points(), 9);
+    }
+
+    #[test]
+    fn test_orientation_does_not_matter() {
+        let clockwise = [
+            (Right, 6),
+            (Down, 5),
+            (Left, 2),
+            (Down, 2),
+            (Left, 4),
+            (Up, 7),
+        ];
+        let anticlockwise = [
+            (Down, 7),
+            (Right, 4),
+            (Up, 2),
+            (Right, 2),
+            (Up, 5),
+            (Left, 6),
+        ];
+        let a = Polygon::from_moves(clockwise).unwrap();
+        let b = Polygon::from_moves(anticlockwise).unwrap();
+        assert_eq!(a.area(), b.area());
+        assert_eq!(a.lattice_points(), b.lattice_points());
+        assert_eq!(a.lattice_points(), 6 * 7 + 2 * 5);
+    }
+
+    #[test]
+    fn test_large() {
+        let side = 1 << 40;
+        let polygon =
+            Polygon::from_moves([(Right, side), (Up, side), (Left, side), (Down, side)]).unwrap();
+        assert_eq!(polygon.lattice_points(), (side as i128 + 1).pow(2));
+    }
+
+    #[test]
+    fn test_unclosed() {
+        assert_eq!(
+            Polygon::from_moves([(Right, 2), (Down, 2), (Left, 2)]),
+            Err(PolygonError::Unclosed { end: (0, -2) })
+        );
+    }
+
+    #[test]
+    fn test_self_intersection() {
+        // A figure of eight whose first edge crosses its fourth at (1, 0).
+        assert_eq!(
+            Polygon::from_moves([
+                (Right, 2),
+                (Down, 2),
+                (Left, 1),
+                (Up, 2),
+                (Left, 1),
+                (Up, 1),
+                (Right, 1),
+                (Down, 1),
+                (Left, 1),
+            ]),
+            Err(PolygonError::SelfIntersection {
+                first: 0,
+                second: 3,
+                at: (1, 0)
+            })
+        );
+    }
+
+    #[test]
+    fn test_touching_corner() {
+        // Two squares meeting at a single corner still touch.
+        assert!(matches!(
+            Polygon::from_moves([
+                (Right, 1),
+                (Down, 1),
+                (Right, 1),
+                (Down, 1),
+                (Left, 1),
+                (Up, 1),
+                (Left, 1),
+                (Up, 1),
+            ]),
+            Err(PolygonError::SelfIntersection { .. })
+        ));
+    }
+
+    #[test]
+    fn test_doubling_back() {
+        assert!(matches!(
+            Polygon::from_moves([(Right, 3), (Left, 1), (Down, 1), (Left, 2), (Up, 1)]),
+            Err(PolygonError::SelfIntersection { .. })
+        ));
+        assert!(matches!(
+            Polygon::from_moves([(Right, 3), (Left, 3)]),
+            Err(PolygonError::SelfIntersection { .. })
+        ));
+        assert_eq!(Polygon::from_moves([]), Err(PolygonError::Degenerate));
+    }
+}
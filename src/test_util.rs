@@ -0,0 +1,28 @@
+//! A small deterministic random number generator for the brute force tests, so they can
+//! generate inputs without pulling in a crate for it.
+
+/// A 64-bit linear congruential generator using Knuth's MMIX constants.
+#[derive(Debug, Clone)]
+pub struct Lcg {
+    seed: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg { seed }
+    }
+
+    /// The next value, from the well mixed high bits of the state.
+    pub fn next_u64(&mut self) -> u64 {
+        self.seed = self
+            .seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.seed >> 33
+    }
+
+    /// A value in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.next_u64() as usize % n
+    }
+}